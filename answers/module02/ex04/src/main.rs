@@ -1,33 +1,573 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::fmt::Write;
+use std::io;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::SystemTime;
+
 enum Command {
     Todo(String),
+    Edit(usize, String),
+    Move(usize, usize),
     Done(usize),
+    List(String),
     Purge,
+    Undo,
+    Redo,
     Quit,
 }
 
 impl Command {
+    fn parse(line: &str) -> Option<Self> {
+        if line.is_empty() || line.trim() == "QUIT" {
+            Some(Self::Quit)
+        } else if let Some(task) = line.strip_prefix("TODO ") {
+            Some(Self::Todo(task.trim().to_string()))
+        } else if let Some(rest) = line.strip_prefix("EDIT ") {
+            let (index, spec) = rest.trim().split_once(' ')?;
+            Some(Self::Edit(index.parse().ok()?, spec.trim().to_string()))
+        } else if let Some(rest) = line.strip_prefix("MOVE ") {
+            let (from, to) = rest.trim().split_once(' ')?;
+            Some(Self::Move(from.parse().ok()?, to.trim().parse().ok()?))
+        } else if let Some(index) = line.strip_prefix("DONE ") {
+            index.trim().parse().ok().map(Self::Done)
+        } else if line.trim() == "LIST" {
+            Some(Self::List(String::new()))
+        } else if let Some(view) = line.strip_prefix("LIST ") {
+            Some(Self::List(view.trim().to_string()))
+        } else if line.trim() == "PURGE" {
+            Some(Self::Purge)
+        } else if line.trim() == "UNDO" {
+            Some(Self::Undo)
+        } else if line.trim() == "REDO" {
+            Some(Self::Redo)
+        } else {
+            None
+        }
+    }
+
     fn prompt() -> Self {
         loop {
-            let line = ftkit::read_line();
-
-            if line.is_empty() || line.trim() == "QUIT" {
-                break Self::Quit;
-            } else if let Some(task) = line.strip_prefix("TODO ") {
-                break Self::Todo(task.trim().to_string());
-            } else if let Some(index) = line.strip_prefix("DONE ") {
-                if let Ok(index) = index.trim().parse() {
-                    break Self::Done(index);
-                }
-            } else if line.trim() == "PURGE" {
-                break Self::Purge;
+            if let Some(command) = Self::parse(&ftkit::read_line()) {
+                break command;
+            }
+        }
+    }
+
+    /// Parses the subcommand passed on the command-line, if any.
+    fn from_args() -> Result<Option<Self>, ArgsError> {
+        if ftkit::ARGS.len() < 2 {
+            return Ok(None);
+        }
+
+        let subcommand: &'static str = &ftkit::ARGS[1];
+        let args: Vec<&'static str> = ftkit::ARGS.into_iter().skip(2).collect();
+
+        let (min_args, max_args) = match subcommand {
+            "add" => (1, usize::MAX),
+            "edit" => (2, usize::MAX),
+            "move" => (2, 2),
+            "done" => (1, 1),
+            "list" => (0, usize::MAX),
+            "purge" | "undo" | "redo" => (0, 0),
+            other => return Err(ArgsError::UnknownSubcommand { arg: other }),
+        };
+
+        if args.len() < min_args {
+            return Err(ArgsError::NotEnoughArguments);
+        }
+        if args.len() > max_args {
+            return Err(ArgsError::TooManyArguments);
+        }
+
+        let command = match subcommand {
+            "add" => Self::Todo(args.join(" ")),
+            "edit" => Self::Edit(parse_index(args[0])?, args[1..].join(" ")),
+            "move" => Self::Move(parse_index(args[0])?, parse_index(args[1])?),
+            "done" => Self::Done(parse_index(args[0])?),
+            "list" => Self::List(args.join(" ")),
+            "purge" => Self::Purge,
+            "undo" => Self::Undo,
+            _ => Self::Redo,
+        };
+
+        Ok(Some(command))
+    }
+}
+
+fn parse_index(arg: &'static str) -> Result<usize, ArgsError> {
+    match arg.parse() {
+        Ok(index) => Ok(index),
+        Err(_) => Err(ArgsError::InvalidIndex { arg }),
+    }
+}
+
+enum ArgsError {
+    UnknownSubcommand { arg: &'static str },
+    InvalidIndex { arg: &'static str },
+    TooManyArguments,
+    NotEnoughArguments,
+}
+
+impl fmt::Display for ArgsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownSubcommand { arg } => write!(f, "'{arg}' is not a valid subcommand"),
+            Self::InvalidIndex { arg } => write!(f, "'{arg}' is not a valid index"),
+            Self::TooManyArguments => f.write_str("too many arguments"),
+            Self::NotEnoughArguments => f.write_str("not enough arguments"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum TodoError {
+    NoSuchTodo {
+        index: usize,
+        count: usize,
+    },
+    EmptyTask,
+    InvalidPriority(String),
+    InvalidDate(String),
+    /// Tags are stored separated by commas, so they cannot contain any.
+    InvalidTag(String),
+    InvalidSortKey(String),
+    InvalidFilter(String),
+    NothingToUndo,
+    NothingToRedo,
+}
+
+impl fmt::Display for TodoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoSuchTodo { index, count: 0 } => {
+                write!(f, "no todo at index {index} (the list is empty)")
+            }
+            Self::NoSuchTodo { index, count } => {
+                write!(f, "no todo at index {index} (expected 0..{count})")
+            }
+            Self::EmptyTask => f.write_str("the task is empty"),
+            Self::InvalidPriority(s) => {
+                write!(f, "'{s}' is not a valid priority (low, normal or high)")
+            }
+            Self::InvalidDate(s) => write!(f, "'{s}' is not a valid date (YYYY-MM-DD)"),
+            Self::InvalidTag(s) => write!(f, "'{s}' is not a valid tag (it contains ',')"),
+            Self::InvalidSortKey(s) => {
+                write!(f, "'{s}' is not a valid sort key (priority or due)")
+            }
+            Self::InvalidFilter(s) => write!(f, "'{s}' is not a valid filter"),
+            Self::NothingToUndo => f.write_str("nothing to undo"),
+            Self::NothingToRedo => f.write_str("nothing to redo"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Priority {
+    Low,
+    Normal,
+    High,
+}
+
+impl Priority {
+    fn parse(s: &str) -> Result<Self, TodoError> {
+        match s {
+            "low" => Ok(Self::Low),
+            "normal" => Ok(Self::Normal),
+            "high" => Ok(Self::High),
+            _ => Err(TodoError::InvalidPriority(s.to_string())),
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Low => "low",
+            Self::Normal => "normal",
+            Self::High => "high",
+        }
+    }
+}
+
+/// A day of the (proleptic) Gregorian calendar.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct Date {
+    year: i32,
+    month: u32,
+    day: u32,
+}
+
+impl Date {
+    fn parse(s: &str) -> Result<Self, TodoError> {
+        let invalid = || TodoError::InvalidDate(s.to_string());
+
+        let mut parts = s.splitn(3, '-');
+        let mut next = |len: usize| match parts.next() {
+            Some(part) if part.len() == len && part.bytes().all(|b| b.is_ascii_digit()) => {
+                Ok(part.parse::<u32>().unwrap())
+            }
+            _ => Err(invalid()),
+        };
+
+        let year = next(4)? as i32;
+        let month = next(2)?;
+        let day = next(2)?;
+
+        if !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month) {
+            return Err(invalid());
+        }
+
+        Ok(Self { year, month, day })
+    }
+
+    /// Returns the date of the `days`-th day after the 1st of January 1970.
+    fn from_days(days: i64) -> Self {
+        // See Howard Hinnant's `civil_from_days`.
+        let z = days + 719468;
+        let era = z.div_euclid(146097);
+        let doe = z - era * 146097;
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+        let year = (yoe + era * 400) as i32 + (month <= 2) as i32;
+
+        Self { year, month, day }
+    }
+
+    /// Returns the current date, in UTC.
+    fn today() -> Self {
+        let secs = match SystemTime::now().duration_since(SystemTime::UNIX_EPOCH) {
+            Ok(elapsed) => elapsed.as_secs() as i64,
+            Err(_) => 0,
+        };
+        Self::from_days(secs / 86400)
+    }
+}
+
+fn days_in_month(year: i32, month: u32) -> u32 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+/// A change to a todo, written as its text followed or interleaved with markers:
+///
+/// - `!low`, `!normal` or `!high` sets the priority,
+/// - `@YYYY-MM-DD` sets the due date, and `@-` removes it,
+/// - `#tag` adds a tag, and `-#tag` removes it. Tags cannot contain `,`.
+///
+/// For example, `buy milk !high @2024-05-01 #groceries`.
+#[derive(Debug, Default, PartialEq)]
+struct TodoSpec {
+    text: String,
+    priority: Option<Priority>,
+    due: Option<Option<Date>>,
+    add_tags: Vec<String>,
+    remove_tags: Vec<String>,
+}
+
+fn parse_tag(tag: &str) -> Result<String, TodoError> {
+    if tag.contains(',') {
+        return Err(TodoError::InvalidTag(tag.to_string()));
+    }
+    Ok(tag.to_string())
+}
+
+impl TodoSpec {
+    fn parse(s: &str) -> Result<Self, TodoError> {
+        let mut spec = Self::default();
+        let mut words = Vec::new();
+
+        for word in s.split_whitespace() {
+            if let Some(priority) = word.strip_prefix('!') {
+                spec.priority = Some(Priority::parse(priority)?);
+            } else if word == "@-" {
+                spec.due = Some(None);
+            } else if let Some(date) = word.strip_prefix('@') {
+                spec.due = Some(Some(Date::parse(date)?));
+            } else if let Some(tag) = word.strip_prefix("-#").filter(|t| !t.is_empty()) {
+                spec.remove_tags.push(parse_tag(tag)?);
+            } else if let Some(tag) = word.strip_prefix('#').filter(|t| !t.is_empty()) {
+                spec.add_tags.push(parse_tag(tag)?);
+            } else {
+                words.push(word);
+            }
+        }
+
+        spec.text = words.join(" ");
+        Ok(spec)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Todo {
+    text: String,
+    priority: Priority,
+    due: Option<Date>,
+    tags: Vec<String>,
+}
+
+impl Todo {
+    fn new(text: String) -> Self {
+        Self {
+            text,
+            priority: Priority::Normal,
+            due: None,
+            tags: Vec::new(),
+        }
+    }
+
+    /// Applies the changes described by `spec`. The text is only replaced when `spec` has one.
+    fn apply(&mut self, spec: TodoSpec) {
+        if !spec.text.is_empty() {
+            self.text = spec.text;
+        }
+        if let Some(priority) = spec.priority {
+            self.priority = priority;
+        }
+        if let Some(due) = spec.due {
+            self.due = due;
+        }
+        self.tags.retain(|tag| !spec.remove_tags.contains(tag));
+        for tag in spec.add_tags {
+            if !self.tags.contains(&tag) {
+                self.tags.push(tag);
+            }
+        }
+    }
+
+    fn is_overdue(&self, today: Date) -> bool {
+        matches!(self.due, Some(due) if due < today)
+    }
+
+    /// Writes the todo in the format read by [`Todo::deserialize`].
+    fn serialize(&self, target: &mut String) {
+        target.push_str(self.priority.name());
+        target.push('\t');
+        match self.due {
+            Some(due) => target.push_str(&due.to_string()),
+            None => target.push('-'),
+        }
+        target.push('\t');
+        target.push_str(&self.tags.join(","));
+        target.push('\t');
+        target.push_str(&self.text);
+    }
+
+    /// Parses a todo written by [`Todo::serialize`]. Lines without metadata are plain tasks.
+    fn deserialize(s: &str) -> Option<Self> {
+        let mut parts = s.splitn(4, '\t');
+        let (Some(priority), Some(due), Some(tags), Some(text)) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Some(Self::new(s.to_string()));
+        };
+
+        Some(Self {
+            text: text.to_string(),
+            priority: Priority::parse(priority).ok()?,
+            due: match due {
+                "-" => None,
+                date => Some(Date::parse(date).ok()?),
+            },
+            tags: tags
+                .split(',')
+                .filter(|tag| !tag.is_empty())
+                .map(String::from)
+                .collect(),
+        })
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+enum SortKey {
+    #[default]
+    Position,
+    Priority,
+    Due,
+}
+
+/// Selects and orders the todos shown by [`TodoList::display`], written as a list of filters:
+///
+/// - `#tag` only keeps todos with that tag,
+/// - `!priority` only keeps todos of at least that priority,
+/// - `@YYYY-MM-DD` only keeps todos due on or before that date,
+/// - `by:priority` or `by:due` sorts the pending todos.
+#[derive(Debug, Default, PartialEq)]
+struct View {
+    tag: Option<String>,
+    priority: Option<Priority>,
+    due_before: Option<Date>,
+    sort: SortKey,
+}
+
+impl View {
+    fn parse(s: &str) -> Result<Self, TodoError> {
+        let mut view = Self::default();
+
+        for word in s.split_whitespace() {
+            if let Some(tag) = word.strip_prefix('#') {
+                view.tag = Some(tag.to_string());
+            } else if let Some(priority) = word.strip_prefix('!') {
+                view.priority = Some(Priority::parse(priority)?);
+            } else if let Some(date) = word.strip_prefix('@') {
+                view.due_before = Some(Date::parse(date)?);
+            } else if let Some(key) = word.strip_prefix("by:") {
+                view.sort = match key {
+                    "priority" => SortKey::Priority,
+                    "due" => SortKey::Due,
+                    _ => return Err(TodoError::InvalidSortKey(key.to_string())),
+                };
+            } else {
+                return Err(TodoError::InvalidFilter(word.to_string()));
+            }
+        }
+
+        Ok(view)
+    }
+
+    fn matches(&self, todo: &Todo) -> bool {
+        if let Some(tag) = &self.tag {
+            if !todo.tags.contains(tag) {
+                return false;
+            }
+        }
+        if let Some(priority) = self.priority {
+            if todo.priority < priority {
+                return false;
+            }
+        }
+        if let Some(date) = self.due_before {
+            if !matches!(todo.due, Some(due) if due <= date) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// A modification of a [`TodoList`], along with what is needed to revert it.
+#[derive(Debug, Clone, PartialEq)]
+enum Change {
+    Add(Todo),
+    Edit {
+        index: usize,
+        before: Todo,
+        after: Todo,
+    },
+    Move {
+        from: usize,
+        to: usize,
+    },
+    Done(usize),
+    Purge(Vec<Todo>),
+}
+
+impl Change {
+    /// Writes the change in the format read by [`Change::deserialize`]: a header line followed
+    /// by one tab-indented line per todo it carries.
+    fn serialize(&self, target: &mut String) {
+        let todos: Vec<&Todo> = match self {
+            Self::Add(todo) => {
+                target.push_str("add");
+                vec![todo]
             }
+            Self::Edit {
+                index,
+                before,
+                after,
+            } => {
+                let _ = write!(target, "edit {index}");
+                vec![before, after]
+            }
+            Self::Move { from, to } => {
+                let _ = write!(target, "move {from} {to}");
+                Vec::new()
+            }
+            Self::Done(index) => {
+                let _ = write!(target, "done {index}");
+                Vec::new()
+            }
+            Self::Purge(dones) => {
+                target.push_str("purge");
+                dones.iter().collect()
+            }
+        };
+        target.push('\n');
+
+        for todo in todos {
+            target.push('\t');
+            todo.serialize(target);
+            target.push('\n');
+        }
+    }
+
+    fn deserialize(header: &str, todos: Vec<Todo>) -> Option<Self> {
+        let mut words = header.split(' ');
+        let kind = words.next()?;
+        let mut index = || words.next()?.parse().ok();
+        let mut todos = todos.into_iter();
+
+        let change = match kind {
+            "add" => Self::Add(todos.next()?),
+            "edit" => Self::Edit {
+                index: index()?,
+                before: todos.next()?,
+                after: todos.next()?,
+            },
+            "move" => Self::Move {
+                from: index()?,
+                to: index()?,
+            },
+            "done" => Self::Done(index()?),
+            "purge" => Self::Purge(todos.by_ref().collect()),
+            _ => return None,
+        };
+
+        match todos.next() {
+            Some(_) => None,
+            None => Some(change),
         }
     }
 }
 
+/// The changes that can be undone and redone.
+#[derive(Default)]
+struct History {
+    /// The changes that can be undone, the most recent last.
+    undo: VecDeque<Change>,
+    /// The changes that have been undone, the most recently undone last.
+    redo: Vec<Change>,
+}
+
+impl History {
+    /// The maximum number of changes that can be undone.
+    const LIMIT: usize = 100;
+
+    fn record(&mut self, change: Change) {
+        self.redo.clear();
+        if self.undo.len() == Self::LIMIT {
+            self.undo.pop_front();
+        }
+        self.undo.push_back(change);
+    }
+}
+
 struct TodoList {
-    todos: Vec<String>,
-    dones: Vec<String>,
+    todos: Vec<Todo>,
+    dones: Vec<Todo>,
+    history: History,
 }
 
 impl TodoList {
@@ -35,50 +575,655 @@ impl TodoList {
         Self {
             todos: Vec::new(),
             dones: Vec::new(),
+            history: History::default(),
         }
     }
 
-    fn display(&self) {
-        if !self.todos.is_empty() || !self.dones.is_empty() {
-            println!();
+    /// Returns the path of the file in which the list is stored between two invocations of the
+    /// command-line interface.
+    ///
+    /// This is `$TODO_FILE` when set, and `.todos` in the current directory otherwise.
+    fn storage_path() -> PathBuf {
+        match std::env::var_os("TODO_FILE") {
+            Some(path) => PathBuf::from(path),
+            None => PathBuf::from(".todos"),
         }
-        for i in 0..self.todos.len() {
-            println!("   \x1B[96m{:>3} \x1B[90m[ ] \x1B[0m{}", i, &self.todos[i]);
+    }
+
+    /// Parses a list previously written by [`TodoList::serialize`].
+    fn deserialize(contents: &str) -> Option<Self> {
+        let mut list = Self::new();
+        let mut lines = contents.lines().peekable();
+
+        while let Some(line) = lines.next() {
+            if let Some(todo) = line.strip_prefix("[ ] ") {
+                list.todos.push(Todo::deserialize(todo)?);
+            } else if let Some(done) = line.strip_prefix("[x] ") {
+                list.dones.push(Todo::deserialize(done)?);
+            } else if let Some((stack, header)) = line.split_once(' ') {
+                let mut todos = Vec::new();
+                while let Some(todo) = lines.next_if(|line| line.starts_with('\t')) {
+                    todos.push(Todo::deserialize(&todo[1..])?);
+                }
+
+                let change = Change::deserialize(header, todos)?;
+                match stack {
+                    "undo" => list.history.undo.push_back(change),
+                    "redo" => list.history.redo.push(change),
+                    _ => return None,
+                }
+            } else {
+                return None;
+            }
+        }
+
+        list.history_is_valid().then_some(list)
+    }
+
+    /// Whether every change of the history can be undone and redone, which is not the case
+    /// when the file was edited by hand or truncated.
+    fn history_is_valid(&self) -> bool {
+        let copy = || Self {
+            todos: self.todos.clone(),
+            dones: self.dones.clone(),
+            history: History::default(),
+        };
+
+        let mut list = copy();
+        for change in self.history.undo.iter().rev() {
+            if !list.can_revert(change) {
+                return false;
+            }
+            list.revert(change);
+        }
+
+        let mut list = copy();
+        for change in self.history.redo.iter().rev() {
+            if !list.can_apply(change) {
+                return false;
+            }
+            list.apply(change);
+        }
+        true
+    }
+
+    fn serialize(&self) -> String {
+        let mut result = String::new();
+        for todo in &self.todos {
+            result.push_str("[ ] ");
+            todo.serialize(&mut result);
+            result.push('\n');
         }
         for done in &self.dones {
-            println!("       \x1B[90m[x] {done}\x1B[0m");
+            result.push_str("[x] ");
+            done.serialize(&mut result);
+            result.push('\n');
+        }
+        for change in &self.history.undo {
+            result.push_str("undo ");
+            change.serialize(&mut result);
+        }
+        for change in &self.history.redo {
+            result.push_str("redo ");
+            change.serialize(&mut result);
+        }
+        result
+    }
+
+    /// Loads the list from the storage file. A missing file is an empty list.
+    fn load() -> io::Result<Self> {
+        let path = Self::storage_path();
+        let contents = match std::fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Self::new()),
+            Err(err) => return Err(err),
+        };
+
+        match Self::deserialize(&contents) {
+            Some(list) => Ok(list),
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("'{}' is not a valid todo file", path.display()),
+            )),
+        }
+    }
+
+    fn save(&self) -> io::Result<()> {
+        std::fs::write(Self::storage_path(), self.serialize())
+    }
+
+    fn is_empty(&self) -> bool {
+        self.todos.is_empty() && self.dones.is_empty()
+    }
+
+    /// Returns the indices of the pending todos selected by `view`, in display order.
+    fn visible(&self, view: &View) -> Vec<usize> {
+        let mut indices: Vec<usize> = (0..self.todos.len())
+            .filter(|&i| view.matches(&self.todos[i]))
+            .collect();
+
+        match view.sort {
+            SortKey::Position => (),
+            SortKey::Priority => {
+                indices.sort_by_key(|&i| std::cmp::Reverse(self.todos[i].priority))
+            }
+            SortKey::Due => {
+                indices.sort_by_key(|&i| (self.todos[i].due.is_none(), self.todos[i].due))
+            }
         }
+
+        indices
     }
 
-    fn add(&mut self, todo: String) {
-        self.todos.push(todo);
+    fn display(&self, view: &View, today: Date) {
+        for i in self.visible(view) {
+            let todo = &self.todos[i];
+            print!("   \x1B[96m{:>3} \x1B[90m[ ] \x1B[0m{}", i, todo.text);
+            match todo.priority {
+                Priority::High => print!(" \x1B[93m!high\x1B[0m"),
+                Priority::Normal => (),
+                Priority::Low => print!(" \x1B[90m!low\x1B[0m"),
+            }
+            for tag in &todo.tags {
+                print!(" \x1B[94m#{tag}\x1B[0m");
+            }
+            match todo.due {
+                Some(due) if todo.is_overdue(today) => print!(" \x1B[91m@{due} (overdue)\x1B[0m"),
+                Some(due) => print!(" \x1B[90m@{due}\x1B[0m"),
+                None => (),
+            }
+            println!();
+        }
+        for done in self.dones.iter().filter(|done| view.matches(done)) {
+            println!("       \x1B[90m[x] {}\x1B[0m", done.text);
+        }
     }
 
-    fn done(&mut self, index: usize) {
+    fn check_index(&self, index: usize) -> Result<(), TodoError> {
         if index >= self.todos.len() {
-            return;
+            return Err(TodoError::NoSuchTodo {
+                index,
+                count: self.todos.len(),
+            });
+        }
+        Ok(())
+    }
+
+    fn apply(&mut self, change: &Change) {
+        match change {
+            Change::Add(todo) => self.todos.push(todo.clone()),
+            Change::Edit { index, after, .. } => self.todos[*index] = after.clone(),
+            Change::Move { from, to } => {
+                let todo = self.todos.remove(*from);
+                self.todos.insert(*to, todo);
+            }
+            Change::Done(index) => {
+                let todo = self.todos.remove(*index);
+                self.dones.push(todo);
+            }
+            Change::Purge(_) => self.dones.clear(),
+        }
+    }
+
+    /// Whether [`TodoList::apply`] can apply `change` without going out of bounds.
+    fn can_apply(&self, change: &Change) -> bool {
+        let len = self.todos.len();
+        match *change {
+            Change::Add(_) | Change::Purge(_) => true,
+            Change::Edit { index, .. } | Change::Done(index) => index < len,
+            Change::Move { from, to } => from < len && to < len,
+        }
+    }
+
+    /// Whether [`TodoList::revert`] can revert `change` without going out of bounds.
+    fn can_revert(&self, change: &Change) -> bool {
+        let len = self.todos.len();
+        match *change {
+            Change::Add(_) => len > 0,
+            Change::Edit { index, .. } => index < len,
+            Change::Move { from, to } => from < len && to < len,
+            Change::Done(index) => !self.dones.is_empty() && index <= len,
+            Change::Purge(_) => true,
+        }
+    }
+
+    fn revert(&mut self, change: &Change) {
+        match change {
+            Change::Add(_) => {
+                self.todos.pop();
+            }
+            Change::Edit { index, before, .. } => self.todos[*index] = before.clone(),
+            Change::Move { from, to } => {
+                let todo = self.todos.remove(*to);
+                self.todos.insert(*from, todo);
+            }
+            Change::Done(index) => {
+                let todo = self.dones.pop().unwrap();
+                self.todos.insert(*index, todo);
+            }
+            Change::Purge(dones) => self.dones.clone_from(dones),
+        }
+    }
+
+    /// Applies `change` and records it in the history.
+    fn perform(&mut self, change: Change) {
+        self.apply(&change);
+        self.history.record(change);
+    }
+
+    fn undo(&mut self) -> Result<(), TodoError> {
+        let change = self
+            .history
+            .undo
+            .pop_back()
+            .ok_or(TodoError::NothingToUndo)?;
+        self.revert(&change);
+        self.history.redo.push(change);
+        Ok(())
+    }
+
+    fn redo(&mut self) -> Result<(), TodoError> {
+        let change = self.history.redo.pop().ok_or(TodoError::NothingToRedo)?;
+        self.apply(&change);
+        self.history.undo.push_back(change);
+        Ok(())
+    }
+
+    fn add(&mut self, spec: TodoSpec) -> Result<(), TodoError> {
+        if spec.text.is_empty() {
+            return Err(TodoError::EmptyTask);
         }
 
-        let s = self.todos.remove(index);
-        self.dones.push(s);
+        let mut todo = Todo::new(String::new());
+        todo.apply(spec);
+        self.perform(Change::Add(todo));
+        Ok(())
+    }
+
+    fn edit(&mut self, index: usize, spec: TodoSpec) -> Result<(), TodoError> {
+        self.check_index(index)?;
+
+        let before = self.todos[index].clone();
+        let mut after = before.clone();
+        after.apply(spec);
+        self.perform(Change::Edit {
+            index,
+            before,
+            after,
+        });
+        Ok(())
+    }
+
+    /// Moves the todo at `from` so that it ends up at index `to`.
+    fn move_todo(&mut self, from: usize, to: usize) -> Result<(), TodoError> {
+        self.check_index(from)?;
+        self.check_index(to)?;
+
+        self.perform(Change::Move { from, to });
+        Ok(())
+    }
+
+    fn done(&mut self, index: usize) -> Result<(), TodoError> {
+        self.check_index(index)?;
+
+        self.perform(Change::Done(index));
+        Ok(())
     }
 
     fn purge(&mut self) {
-        self.dones.clear();
+        self.perform(Change::Purge(self.dones.clone()));
+    }
+
+    /// Executes a command that modifies the list. Other commands are ignored.
+    fn execute(&mut self, command: Command) -> Result<(), TodoError> {
+        match command {
+            Command::Todo(spec) => self.add(TodoSpec::parse(&spec)?),
+            Command::Edit(index, spec) => self.edit(index, TodoSpec::parse(&spec)?),
+            Command::Move(from, to) => self.move_todo(from, to),
+            Command::Done(index) => self.done(index),
+            Command::Purge => {
+                self.purge();
+                Ok(())
+            }
+            Command::Undo => self.undo(),
+            Command::Redo => self.redo(),
+            Command::List(_) | Command::Quit => Ok(()),
+        }
     }
 }
 
-fn main() {
+/// Runs the interactive prompt until the user quits.
+fn repl() {
     let mut todolist = TodoList::new();
+    let mut view = View::default();
 
     loop {
-        todolist.display();
+        if !todolist.is_empty() {
+            println!();
+        }
+        todolist.display(&view, Date::today());
         println!();
-        match Command::prompt() {
-            Command::Todo(todo) => todolist.add(todo),
-            Command::Done(index) => todolist.done(index),
-            Command::Purge => todolist.purge(),
+        let result = match Command::prompt() {
+            Command::List(filters) => View::parse(&filters).map(|new_view| view = new_view),
             Command::Quit => break,
+            command => todolist.execute(command),
+        };
+        if let Err(err) = result {
+            eprintln!("error: {err}");
+        }
+    }
+}
+
+/// Executes a single command against the stored list.
+fn run(command: Command) -> ExitCode {
+    let mut todolist = match TodoList::load() {
+        Ok(ok) => ok,
+        Err(err) => {
+            eprintln!("error: failed to load the todo list: {err}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    if let Command::List(filters) = &command {
+        return match View::parse(filters) {
+            Ok(view) => {
+                todolist.display(&view, Date::today());
+                ExitCode::SUCCESS
+            }
+            Err(err) => {
+                eprintln!("error: {err}");
+                ExitCode::FAILURE
+            }
+        };
+    }
+
+    if let Err(err) = todolist.execute(command) {
+        eprintln!("error: {err}");
+        return ExitCode::FAILURE;
+    }
+
+    match todolist.save() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: failed to save the todo list: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    match Command::from_args() {
+        Ok(Some(command)) => run(command),
+        Ok(None) => {
+            repl();
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("error: {err}");
+            eprintln!(
+                "usage: {} [add <task> | edit <index> <changes> | move <from> <to> | done <index> | list [filters] | purge | undo | redo]",
+                &ftkit::ARGS[0]
+            );
+            ExitCode::from(2)
+        }
+    }
+}
+
+#[cfg(test)]
+fn spec(s: &str) -> TodoSpec {
+    TodoSpec::parse(s).unwrap()
+}
+
+#[cfg(test)]
+fn texts(todos: &[Todo]) -> Vec<&str> {
+    todos.iter().map(|todo| todo.text.as_str()).collect()
+}
+
+#[cfg(test)]
+#[test]
+fn done_out_of_range() {
+    let mut list = TodoList::new();
+    assert_eq!(
+        list.done(0),
+        Err(TodoError::NoSuchTodo { index: 0, count: 0 })
+    );
+
+    list.add(spec("a")).unwrap();
+    list.add(spec("b")).unwrap();
+    assert_eq!(
+        list.done(2),
+        Err(TodoError::NoSuchTodo { index: 2, count: 2 })
+    );
+    assert_eq!(list.done(1), Ok(()));
+    assert_eq!(texts(&list.todos), ["a"]);
+    assert_eq!(texts(&list.dones), ["b"]);
+}
+
+#[cfg(test)]
+#[test]
+fn serialize_roundtrip() {
+    let mut list = TodoList::new();
+    list.add(spec("buy milk !high @2024-02-29 #groceries #home"))
+        .unwrap();
+    list.add(spec("walk the dog")).unwrap();
+    list.add(spec("[x] tricky !low")).unwrap();
+    list.done(0).unwrap();
+    list.history = History::default();
+
+    let text = list.serialize();
+    assert_eq!(
+        text,
+        "[ ] normal\t-\t\twalk the dog\n\
+         [ ] low\t-\t\t[x] tricky\n\
+         [x] high\t2024-02-29\tgroceries,home\tbuy milk\n"
+    );
+
+    let back = TodoList::deserialize(&text).unwrap();
+    assert_eq!(back.todos, list.todos);
+    assert_eq!(back.dones, list.dones);
+
+    let legacy = TodoList::deserialize("[ ] plain task\n[x] old\n").unwrap();
+    assert_eq!(legacy.todos, [Todo::new("plain task".into())]);
+    assert_eq!(legacy.dones, [Todo::new("old".into())]);
+
+    assert!(TodoList::deserialize("nope\n").is_none());
+}
+
+#[cfg(test)]
+#[test]
+fn parse_spec() {
+    assert_eq!(
+        spec("call  mom !high @2024-05-01 #family -#work"),
+        TodoSpec {
+            text: "call mom".into(),
+            priority: Some(Priority::High),
+            due: Some(Some(Date {
+                year: 2024,
+                month: 5,
+                day: 1
+            })),
+            add_tags: vec!["family".into()],
+            remove_tags: vec!["work".into()],
         }
+    );
+    assert_eq!(spec("@-").due, Some(None));
+    assert_eq!(
+        TodoSpec::parse("x !urgent"),
+        Err(TodoError::InvalidPriority("urgent".into()))
+    );
+    assert_eq!(
+        TodoSpec::parse("x @2023-02-29"),
+        Err(TodoError::InvalidDate("2023-02-29".into()))
+    );
+    assert_eq!(
+        TodoSpec::parse("x #a,b"),
+        Err(TodoError::InvalidTag("a,b".into()))
+    );
+    assert_eq!(
+        TodoSpec::parse("x -#a,"),
+        Err(TodoError::InvalidTag("a,".into()))
+    );
+}
+
+#[cfg(test)]
+#[test]
+fn edit_and_move() {
+    let mut list = TodoList::new();
+    assert_eq!(list.add(spec("#tag")), Err(TodoError::EmptyTask));
+    list.add(spec("a #x #y")).unwrap();
+    list.add(spec("b")).unwrap();
+    list.add(spec("c")).unwrap();
+
+    list.edit(0, spec("!low -#x #z @2030-01-01")).unwrap();
+    assert_eq!(list.todos[0].text, "a");
+    assert_eq!(list.todos[0].priority, Priority::Low);
+    assert_eq!(list.todos[0].tags, ["y", "z"]);
+    list.edit(0, spec("renamed @-")).unwrap();
+    assert_eq!(list.todos[0].text, "renamed");
+    assert_eq!(list.todos[0].due, None);
+
+    list.move_todo(0, 2).unwrap();
+    assert_eq!(texts(&list.todos), ["b", "c", "renamed"]);
+    list.move_todo(2, 1).unwrap();
+    assert_eq!(texts(&list.todos), ["b", "renamed", "c"]);
+    assert_eq!(
+        list.move_todo(0, 3),
+        Err(TodoError::NoSuchTodo { index: 3, count: 3 })
+    );
+}
+
+#[cfg(test)]
+#[test]
+fn filter_and_sort() {
+    let mut list = TodoList::new();
+    list.add(spec("a !low @2024-03-01 #work")).unwrap();
+    list.add(spec("b !high #home")).unwrap();
+    list.add(spec("c @2024-01-15 #work")).unwrap();
+    list.add(spec("d !high @2024-02-01 #work")).unwrap();
+
+    let view = |s: &str| View::parse(s).unwrap();
+    assert_eq!(list.visible(&view("")), [0, 1, 2, 3]);
+    assert_eq!(list.visible(&view("#work")), [0, 2, 3]);
+    assert_eq!(list.visible(&view("!normal")), [1, 2, 3]);
+    assert_eq!(list.visible(&view("@2024-02-01")), [2, 3]);
+    assert_eq!(list.visible(&view("by:priority")), [1, 3, 2, 0]);
+    assert_eq!(list.visible(&view("by:due")), [2, 3, 0, 1]);
+    assert_eq!(list.visible(&view("#work by:due !normal")), [2, 3]);
+    assert!(View::parse("by:name").is_err());
+    assert_eq!(
+        View::parse("work"),
+        Err(TodoError::InvalidFilter("work".into()))
+    );
+
+    let today = Date::parse("2024-02-01").unwrap();
+    assert!(list.todos[2].is_overdue(today));
+    assert!(!list.todos[3].is_overdue(today));
+    assert!(!list.todos[1].is_overdue(today));
+}
+
+#[cfg(test)]
+#[test]
+fn date_from_days() {
+    assert_eq!(Date::from_days(0).to_string(), "1970-01-01");
+    assert_eq!(Date::from_days(19782).to_string(), "2024-02-29");
+    assert_eq!(Date::from_days(-1).to_string(), "1969-12-31");
+}
+
+#[cfg(test)]
+#[test]
+fn undo_redo() {
+    let mut list = TodoList::new();
+    assert_eq!(list.undo(), Err(TodoError::NothingToUndo));
+
+    list.add(spec("a")).unwrap();
+    list.add(spec("b #x")).unwrap();
+    list.add(spec("c")).unwrap();
+    list.edit(1, spec("!high")).unwrap();
+    list.move_todo(0, 2).unwrap();
+    list.done(0).unwrap();
+    list.done(0).unwrap();
+    list.purge();
+    assert_eq!(texts(&list.todos), ["a"]);
+    assert!(list.dones.is_empty());
+
+    let snapshots = [
+        (vec!["a"], vec!["b", "c"]),
+        (vec!["c", "a"], vec!["b"]),
+        (vec!["b", "c", "a"], vec![]),
+        (vec!["a", "b", "c"], vec![]),
+    ];
+    for (todos, dones) in &snapshots {
+        list.undo().unwrap();
+        assert_eq!(&texts(&list.todos), todos);
+        assert_eq!(&texts(&list.dones), dones);
+    }
+    assert_eq!(list.todos[1].priority, Priority::High);
+    list.undo().unwrap();
+    assert_eq!(list.todos[1].priority, Priority::Normal);
+
+    list.redo().unwrap();
+    list.redo().unwrap();
+    list.redo().unwrap();
+    assert_eq!(texts(&list.todos), ["c", "a"]);
+    assert_eq!(texts(&list.dones), ["b"]);
+
+    list.add(spec("d")).unwrap();
+    assert_eq!(list.redo(), Err(TodoError::NothingToRedo));
+}
+
+#[cfg(test)]
+#[test]
+fn history_is_bounded() {
+    let mut list = TodoList::new();
+    for i in 0..History::LIMIT + 10 {
+        list.add(spec(&i.to_string())).unwrap();
+    }
+
+    while list.undo().is_ok() {}
+    assert_eq!(list.todos.len(), 10);
+    assert_eq!(list.history.redo.len(), History::LIMIT);
+}
+
+#[cfg(test)]
+#[test]
+fn history_roundtrip() {
+    let mut list = TodoList::new();
+    list.add(spec("a #x")).unwrap();
+    list.add(spec("b")).unwrap();
+    list.edit(0, spec("!low @2024-01-01")).unwrap();
+    list.move_todo(1, 0).unwrap();
+    list.done(1).unwrap();
+    list.purge();
+    list.undo().unwrap();
+    list.undo().unwrap();
+
+    let mut back = TodoList::deserialize(&list.serialize()).unwrap();
+    assert_eq!(back.history.undo, list.history.undo);
+    assert_eq!(back.history.redo, list.history.redo);
+
+    while back.undo().is_ok() {}
+    assert!(back.todos.is_empty());
+    while back.redo().is_ok() {}
+    assert_eq!(texts(&back.todos), ["b"]);
+    assert!(back.dones.is_empty());
+}
+
+#[cfg(test)]
+#[test]
+fn invalid_history() {
+    let todo = "normal\t-\t\ta\n";
+    assert!(TodoList::deserialize(&format!("[ ] {todo}undo edit 0\n\t{todo}\t{todo}")).is_some());
+    for history in [
+        // Indices past the end of the list.
+        format!("undo edit 1\n\t{todo}\t{todo}"),
+        "undo move 0 3\n".to_string(),
+        "redo done 5\n".to_string(),
+        "redo move 2 0\n".to_string(),
+        // Nothing to take back from the list.
+        format!("undo add\n\t{todo}undo add\n\t{todo}"),
+        "undo done 0\n".to_string(),
+    ] {
+        let contents = format!("[ ] {todo}{history}");
+        assert!(TodoList::deserialize(&contents).is_none(), "{history}");
     }
 }
@@ -1,5 +1,9 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr;
+
 /// A color, represented by its red, green, and blue components.
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Color {
     /// The amount of red light.
     pub red: u8,
@@ -29,11 +33,6 @@ impl Color {
     ///
     /// `self` has an opacity of `alpha`.
     fn over(self, alpha: u8, other: Self) -> Self {
-        #[inline(always)]
-        fn over_component(a: u8, alpha: u8, b: u8) -> u8 {
-            ((a as u16 * alpha as u16 + b as u16 * (255 - alpha) as u16) / 255) as u8
-        }
-
         Self {
             red: over_component(self.red, alpha, other.red),
             green: over_component(self.green, alpha, other.green),
@@ -51,27 +50,639 @@ impl Color {
 
     /// Mixes up to `max` colors extracted from `palette` to produce `self`. The closest color is
     /// returned.
+    ///
+    /// This is the same as calling [`Color::closest_mix_with`] with [`Search::Exact`] and
+    /// [`Metric::Rgb`]. The cost is exponential in `max`, so [`Color::approximate_mix`] should be
+    /// preferred on large palettes or for many layers.
     pub fn closest_mix(self, palette: &[(Self, u8)], max: u32) -> Self {
-        fn best_color(target: Color, palette: &[(Color, u8)], base: Color, max: u32) -> Color {
-            if max == 0 {
-                return base;
+        self.closest_mix_with(palette, max, Search::Exact, Metric::Rgb)
+    }
+
+    /// Like [`Color::closest_mix`], but switches to a beam search when trying every mix would be
+    /// too slow, as chosen by [`Search::suggested`]. The returned color is then only close to
+    /// `self`, and not always the closest possible one.
+    pub fn approximate_mix(self, palette: &[(Self, u8)], max: u32) -> Self {
+        let search = Search::suggested(palette.len(), max);
+        self.closest_mix_with(palette, max, search, Metric::Rgb)
+    }
+
+    /// Mixes up to `max` colors extracted from `palette` to produce `self`, exploring the
+    /// possible mixes as specified by `search` and comparing them with `metric`.
+    pub fn closest_mix_with(
+        self,
+        palette: &[(Self, u8)],
+        max: u32,
+        search: Search,
+        metric: Metric,
+    ) -> Self {
+        self.closest_mix_recipe(palette, max, search, metric).color
+    }
+
+    /// Like [`Color::closest_mix_with`], but also returns the layers that produce the mix.
+    pub fn closest_mix_recipe(
+        self,
+        palette: &[(Self, u8)],
+        max: u32,
+        search: Search,
+        metric: Metric,
+    ) -> Mix {
+        match search {
+            Search::Exact => exact_mix(self, palette, max, metric),
+            Search::Beam { width } => beam_mix(self, palette, max, width, metric),
+        }
+    }
+
+    /// Puts the colors of `palette` designated by `layers` over white, in order.
+    ///
+    /// # Panics
+    ///
+    /// If one of the `layers` is not an index of `palette`.
+    pub fn from_layers(palette: &[(Self, u8)], layers: &[usize]) -> Self {
+        layers.iter().fold(Self::WHITE, |base, &i| {
+            let (p, o) = palette[i];
+            p.over(o, base)
+        })
+    }
+}
+
+/// An error which can occur when parsing a [`Color`] from its hexadecimal notation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseColorError {
+    /// The string does not start with `#`.
+    MissingHash,
+    /// The string does not have 3 or 6 digits.
+    InvalidLength,
+    /// The character at the given byte position is not a hexadecimal digit.
+    InvalidDigit(usize),
+}
+
+impl fmt::Display for ParseColorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingHash => f.write_str("missing '#'"),
+            Self::InvalidLength => f.write_str("expected 3 or 6 hexadecimal digits"),
+            Self::InvalidDigit(pos) => write!(f, "invalid hexadecimal digit at position {pos}"),
+        }
+    }
+}
+
+impl FromStr for Color {
+    type Err = ParseColorError;
+
+    /// Parses a color written as `#RRGGBB` or `#RGB`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let digits = s.strip_prefix('#').ok_or(ParseColorError::MissingHash)?;
+
+        let mut values = [0u8; 6];
+        for (i, b) in digits.bytes().enumerate() {
+            let value = match b {
+                b'0'..=b'9' => b - b'0',
+                b'a'..=b'f' => b - b'a' + 10,
+                b'A'..=b'F' => b - b'A' + 10,
+                _ => return Err(ParseColorError::InvalidDigit(i + 1)),
+            };
+            if let Some(slot) = values.get_mut(i) {
+                *slot = value;
             }
+        }
+
+        match digits.len() {
+            3 => Ok(Self::new(values[0] * 17, values[1] * 17, values[2] * 17)),
+            6 => Ok(Self::new(
+                values[0] << 4 | values[1],
+                values[2] << 4 | values[3],
+                values[4] << 4 | values[5],
+            )),
+            _ => Err(ParseColorError::InvalidLength),
+        }
+    }
+}
+
+/// Formats the color as `#RRGGBB`.
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{:02X}{:02X}{:02X}", self.red, self.green, self.blue)
+    }
+}
+
+/// A color in the HSL (hue, saturation, lightness) color space.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Hsl {
+    /// The hue, in degrees, in `0.0..360.0`.
+    pub hue: f32,
+    /// The saturation, in `0.0..=1.0`.
+    pub saturation: f32,
+    /// The lightness, in `0.0..=1.0`.
+    pub lightness: f32,
+}
+
+/// A color in the HSV (hue, saturation, value) color space.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Hsv {
+    /// The hue, in degrees, in `0.0..360.0`.
+    pub hue: f32,
+    /// The saturation, in `0.0..=1.0`.
+    pub saturation: f32,
+    /// The value, in `0.0..=1.0`.
+    pub value: f32,
+}
+
+/// A color whose components are proportional to the amount of light, in `0.0..=1.0`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LinearRgb {
+    pub red: f32,
+    pub green: f32,
+    pub blue: f32,
+}
+
+/// A color in the CIE L\*a\*b\* color space, relative to the D65 white point.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Lab {
+    /// The lightness, in `0.0..=100.0`.
+    pub l: f32,
+    /// The position between green (negative) and red (positive).
+    pub a: f32,
+    /// The position between blue (negative) and yellow (positive).
+    pub b: f32,
+}
 
-            let mut best_so_far = Color::WHITE;
-            let mut best_distance = u32::MAX;
-            for &(p, o) in palette {
-                let candidate = best_color(target, palette, p.over(o, base), max - 1);
-                let dist = candidate.distance(target);
-                if dist <= best_distance {
-                    best_distance = dist;
-                    best_so_far = candidate;
+impl Color {
+    /// Returns the hue, in degrees, along with the largest and smallest components, in
+    /// `0.0..=1.0`.
+    fn hue_max_min(self) -> (f32, f32, f32) {
+        let r = self.red as f32 / 255.0;
+        let g = self.green as f32 / 255.0;
+        let b = self.blue as f32 / 255.0;
+        let max = r.max(g).max(b);
+        let min = r.min(g).min(b);
+        let delta = max - min;
+
+        let hue = if delta == 0.0 {
+            0.0
+        } else if max == r {
+            60.0 * ((g - b) / delta).rem_euclid(6.0)
+        } else if max == g {
+            60.0 * ((b - r) / delta + 2.0)
+        } else {
+            60.0 * ((r - g) / delta + 4.0)
+        };
+
+        (hue, max, min)
+    }
+
+    /// Converts this color to the HSL color space.
+    pub fn to_hsl(self) -> Hsl {
+        let (hue, max, min) = self.hue_max_min();
+        let lightness = (max + min) / 2.0;
+        let saturation = if max == min {
+            0.0
+        } else {
+            (max - min) / (1.0 - (2.0 * lightness - 1.0).abs())
+        };
+
+        Hsl {
+            hue,
+            saturation,
+            lightness,
+        }
+    }
+
+    /// Converts this color to the HSV color space.
+    pub fn to_hsv(self) -> Hsv {
+        let (hue, max, min) = self.hue_max_min();
+        let saturation = if max == 0.0 { 0.0 } else { (max - min) / max };
+
+        Hsv {
+            hue,
+            saturation,
+            value: max,
+        }
+    }
+
+    /// Removes the sRGB gamma curve from this color.
+    pub fn to_linear(self) -> LinearRgb {
+        fn linear_component(c: u8) -> f32 {
+            let c = c as f32 / 255.0;
+            if c <= 0.04045 {
+                c / 12.92
+            } else {
+                ((c + 0.055) / 1.055).powf(2.4)
+            }
+        }
+
+        LinearRgb {
+            red: linear_component(self.red),
+            green: linear_component(self.green),
+            blue: linear_component(self.blue),
+        }
+    }
+
+    /// Converts this color to the CIE L\*a\*b\* color space.
+    pub fn to_lab(self) -> Lab {
+        fn f(t: f32) -> f32 {
+            const DELTA: f32 = 6.0 / 29.0;
+            if t > DELTA * DELTA * DELTA {
+                t.cbrt()
+            } else {
+                t / (3.0 * DELTA * DELTA) + 4.0 / 29.0
+            }
+        }
+
+        let LinearRgb { red, green, blue } = self.to_linear();
+        let x = (0.4124564 * red + 0.3575761 * green + 0.1804375 * blue) / 0.95047;
+        let y = 0.2126729 * red + 0.7151522 * green + 0.0721750 * blue;
+        let z = (0.0193339 * red + 0.119192 * green + 0.9503041 * blue) / 1.08883;
+
+        Lab {
+            l: 116.0 * f(y) - 16.0,
+            a: 500.0 * (f(x) - f(y)),
+            b: 200.0 * (f(y) - f(z)),
+        }
+    }
+}
+
+impl Lab {
+    /// The CIE76 color difference (ΔE\*76), which is the euclidean distance between two colors.
+    pub fn cie76(self, other: Self) -> f32 {
+        let dl = self.l - other.l;
+        let da = self.a - other.a;
+        let db = self.b - other.b;
+        (dl * dl + da * da + db * db).sqrt()
+    }
+
+    /// The CIEDE2000 color difference (ΔE\*00).
+    pub fn ciede2000(self, other: Self) -> f32 {
+        let pow7 = |x: f32| x.powi(7);
+        let chroma = |a: f32, b: f32| (a * a + b * b).sqrt();
+        let hue = |a: f32, b: f32| {
+            if a == 0.0 && b == 0.0 {
+                0.0
+            } else {
+                b.atan2(a).to_degrees().rem_euclid(360.0)
+            }
+        };
+
+        let c_mean = (chroma(self.a, self.b) + chroma(other.a, other.b)) / 2.0;
+        let g = 0.5 * (1.0 - (pow7(c_mean) / (pow7(c_mean) + pow7(25.0))).sqrt());
+        let a1 = (1.0 + g) * self.a;
+        let a2 = (1.0 + g) * other.a;
+        let c1 = chroma(a1, self.b);
+        let c2 = chroma(a2, other.b);
+        let h1 = hue(a1, self.b);
+        let h2 = hue(a2, other.b);
+
+        let dl = other.l - self.l;
+        let dc = c2 - c1;
+        let dh = if c1 * c2 == 0.0 {
+            0.0
+        } else if (h2 - h1).abs() <= 180.0 {
+            h2 - h1
+        } else if h2 - h1 > 180.0 {
+            h2 - h1 - 360.0
+        } else {
+            h2 - h1 + 360.0
+        };
+        let dh = 2.0 * (c1 * c2).sqrt() * (dh / 2.0).to_radians().sin();
+
+        let l_mean = (self.l + other.l) / 2.0;
+        let c_mean = (c1 + c2) / 2.0;
+        let h_mean = if c1 * c2 == 0.0 {
+            h1 + h2
+        } else if (h1 - h2).abs() <= 180.0 {
+            (h1 + h2) / 2.0
+        } else if h1 + h2 < 360.0 {
+            (h1 + h2 + 360.0) / 2.0
+        } else {
+            (h1 + h2 - 360.0) / 2.0
+        };
+
+        let t = 1.0 - 0.17 * (h_mean - 30.0).to_radians().cos()
+            + 0.24 * (2.0 * h_mean).to_radians().cos()
+            + 0.32 * (3.0 * h_mean + 6.0).to_radians().cos()
+            - 0.20 * (4.0 * h_mean - 63.0).to_radians().cos();
+        let d_theta = 30.0 * (-((h_mean - 275.0) / 25.0).powi(2)).exp();
+        let r_c = 2.0 * (pow7(c_mean) / (pow7(c_mean) + pow7(25.0))).sqrt();
+        let l50 = (l_mean - 50.0).powi(2);
+        let s_l = 1.0 + 0.015 * l50 / (20.0 + l50).sqrt();
+        let s_c = 1.0 + 0.045 * c_mean;
+        let s_h = 1.0 + 0.015 * c_mean * t;
+        let r_t = -(2.0 * d_theta).to_radians().sin() * r_c;
+
+        let l = dl / s_l;
+        let c = dc / s_c;
+        let h = dh / s_h;
+        (l * l + c * c + h * h + r_t * c * h).sqrt()
+    }
+}
+
+/// A way to measure how different two colors look.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Metric {
+    /// The squared euclidean distance between the red, green and blue components.
+    ///
+    /// This is cheap to compute, but poorly reflects how different colors are perceived.
+    #[default]
+    Rgb,
+    /// See [`Lab::cie76`].
+    Cie76,
+    /// See [`Lab::ciede2000`].
+    Ciede2000,
+}
+
+impl Metric {
+    /// Measures the difference between `a` and `b`.
+    pub fn distance(self, a: Color, b: Color) -> f32 {
+        match self {
+            Self::Rgb => a.distance(b) as f32,
+            Self::Cie76 => a.to_lab().cie76(b.to_lab()),
+            Self::Ciede2000 => a.to_lab().ciede2000(b.to_lab()),
+        }
+    }
+}
+
+/// A mix of colors, as returned by [`Color::closest_mix_recipe`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mix {
+    /// The indices within the palette of the colors to put over white, from the first layer to
+    /// the last.
+    pub layers: Vec<usize>,
+    /// The color produced by the layers.
+    pub color: Color,
+    /// The distance between `color` and the target color, as measured by the [`Metric`] used to
+    /// find the mix.
+    pub distance: f32,
+}
+
+/// Computes `a` over `b`, `a` having an opacity of `alpha`.
+#[inline(always)]
+fn over_component(a: u8, alpha: u8, b: u8) -> u8 {
+    ((a as u16 * alpha as u16 + b as u16 * (255 - alpha) as u16) / 255) as u8
+}
+
+/// The way [`Color::closest_mix_with`] explores the possible mixes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Search {
+    /// Finds the best possible mix.
+    ///
+    /// Intermediate colors are only explored once per number of remaining layers. With
+    /// [`Metric::Rgb`], those that cannot possibly lead to a better mix than the best one found
+    /// so far are skipped as well. This is usually much faster than trying every sequence of
+    /// layers, but the cost is still exponential in `max`: depth 20 on a palette of 10 colors
+    /// can take tens of seconds.
+    Exact,
+    /// Only keeps the `width` intermediate colors closest to the target after each layer.
+    ///
+    /// This is much faster than [`Search::Exact`] on large palettes, but may miss the best
+    /// mix.
+    Beam { width: usize },
+}
+
+/// The number of sequences of layers under which [`Search::suggested`] tries them all.
+const EXACT_SEQUENCES: usize = 1 << 20;
+
+/// The number of layers left under which [`Search::Exact`] prunes colors with a [`Bound`].
+/// Each layer of the bound takes about 800 KB.
+const BOUND_LAYERS: u32 = 16;
+
+impl Search {
+    /// The beam width used by [`Search::suggested`].
+    pub const DEFAULT_WIDTH: usize = 64;
+
+    /// Returns [`Search::Exact`] when there are at most about a million sequences of up to `max`
+    /// layers from a palette of `len` colors, and a [`Search::Beam`] of
+    /// [`Search::DEFAULT_WIDTH`] otherwise, which takes a few milliseconds per layer.
+    pub fn suggested(len: usize, max: u32) -> Self {
+        match len.checked_pow(max) {
+            Some(sequences) if sequences <= EXACT_SEQUENCES => Self::Exact,
+            _ => Self::Beam {
+                width: Self::DEFAULT_WIDTH,
+            },
+        }
+    }
+}
+
+fn exact_mix(target: Color, palette: &[(Color, u8)], max: u32, metric: Metric) -> Mix {
+    // Any mix found by a beam search is a valid upper bound for the best distance.
+    let beam = beam_mix(target, palette, max, 16, metric);
+    if palette.is_empty() {
+        return beam;
+    }
+
+    let mut search = ExactSearch {
+        target,
+        palette,
+        metric,
+        // Trying every sequence of few layers is faster than computing the bound.
+        bound: match palette.len().checked_pow(max) {
+            Some(sequences) if sequences <= 1 << 12 => None,
+            _ if metric == Metric::Rgb => Some(Bound::new(target, palette, max.min(BOUND_LAYERS))),
+            _ => None,
+        },
+        visited: HashSet::new(),
+        path: Vec::new(),
+        limit: beam.distance,
+        best: None,
+    };
+    search.explore(Color::WHITE, max);
+    search.best.unwrap_or(beam)
+}
+
+/// A lower bound on the distance between the target and any mix that can be reached from a
+/// color with some number of layers left.
+///
+/// Components are considered two by two, as if the best layers for the red and green
+/// components did not have to be the same as the best layers for the blue one. Colors with more
+/// layers left than the bound was computed for are not bounded.
+struct Bound {
+    /// `singles[k][c][v]` is the smallest squared difference between the target's `c`-th
+    /// component and what a component `v` can become after `k` layers.
+    singles: Vec<[[u32; 256]; 3]>,
+    /// `pairs[k][i][u * 256 + v]` is the same for the `i`-th pair of components, `(u, v)`
+    /// being the values of the pair's components.
+    pairs: Vec<[Vec<u32>; 3]>,
+}
+
+/// The pairs of components considered by [`Bound`], along with the remaining component.
+const PAIRS: [(usize, usize, usize); 3] = [(0, 1, 2), (0, 2, 1), (1, 2, 0)];
+
+impl Bound {
+    fn new(target: Color, palette: &[(Color, u8)], max: u32) -> Self {
+        let target = [target.red, target.green, target.blue];
+        let palette: Vec<([u8; 3], u8)> = palette
+            .iter()
+            .map(|&(p, o)| ([p.red, p.green, p.blue], o))
+            .collect();
+
+        let mut single = [[0; 256]; 3];
+        for (c, row) in single.iter_mut().enumerate() {
+            for (v, slot) in row.iter_mut().enumerate() {
+                let d = v as i32 - target[c] as i32;
+                *slot = (d * d) as u32;
+            }
+        }
+        let mut pair: [Vec<u32>; 3] = Default::default();
+        for (i, &(a, b, _)) in PAIRS.iter().enumerate() {
+            pair[i] = (0..256 * 256)
+                .map(|uv| single[a][uv / 256] + single[b][uv % 256])
+                .collect();
+        }
+
+        let mut singles = vec![single];
+        let mut pairs = vec![pair];
+        for _ in 0..max {
+            let prev = singles.last().unwrap();
+            let mut next = [[u32::MAX; 256]; 3];
+            for &(p, o) in &palette {
+                for (c, row) in next.iter_mut().enumerate() {
+                    for (v, slot) in row.iter_mut().enumerate() {
+                        let after = over_component(p[c], o, v as u8);
+                        *slot = (*slot).min(prev[c][after as usize]);
+                    }
                 }
             }
+            singles.push(next);
 
-            best_so_far
+            let prev = pairs.last().unwrap();
+            let mut next: [Vec<u32>; 3] = Default::default();
+            for (i, &(a, b, _)) in PAIRS.iter().enumerate() {
+                next[i] = vec![u32::MAX; 256 * 256];
+                for &(p, o) in &palette {
+                    let after_a: Vec<usize> = (0..=255)
+                        .map(|v| over_component(p[a], o, v) as usize)
+                        .collect();
+                    let after_b: Vec<usize> = (0..=255)
+                        .map(|v| over_component(p[b], o, v) as usize)
+                        .collect();
+                    for u in 0..256 {
+                        for v in 0..256 {
+                            let dist = prev[i][after_a[u] * 256 + after_b[v]];
+                            let slot = &mut next[i][u * 256 + v];
+                            *slot = (*slot).min(dist);
+                        }
+                    }
+                }
+            }
+            pairs.push(next);
         }
 
-        best_color(self, palette, Self::WHITE, max)
+        Self { singles, pairs }
+    }
+
+    fn get(&self, color: Color, left: u32) -> u32 {
+        if left as usize >= self.singles.len() {
+            return 0;
+        }
+        let color = [
+            color.red as usize,
+            color.green as usize,
+            color.blue as usize,
+        ];
+        let singles = &self.singles[left as usize];
+        let pairs = &self.pairs[left as usize];
+
+        PAIRS
+            .iter()
+            .enumerate()
+            .map(|(i, &(a, b, c))| pairs[i][color[a] * 256 + color[b]] + singles[c][color[c]])
+            .max()
+            .unwrap()
+    }
+}
+
+/// A depth-first branch-and-bound search for the best mix.
+///
+/// Layers are tried from the last palette entry to the first and a mix only replaces the best
+/// one when it is strictly closer, so the best mix found is the one that comes last in palette
+/// order, like in an exhaustive search. The same color reached twice with the same number of
+/// layers left only needs to be explored the first time: the second path comes earlier in
+/// palette order and cannot win a tie.
+struct ExactSearch<'a> {
+    target: Color,
+    palette: &'a [(Color, u8)],
+    metric: Metric,
+    /// Only available for [`Metric::Rgb`].
+    bound: Option<Bound>,
+    visited: HashSet<(Color, u32)>,
+    /// The layers leading to the color being explored.
+    path: Vec<usize>,
+    /// The distance that the first mix found must not exceed.
+    limit: f32,
+    best: Option<Mix>,
+}
+
+impl ExactSearch<'_> {
+    /// Whether a mix at `distance` from the target would replace the best mix found so far.
+    fn beats(&self, distance: f32) -> bool {
+        match &self.best {
+            Some(best) => distance < best.distance,
+            None => distance <= self.limit,
+        }
+    }
+
+    fn explore(&mut self, base: Color, left: u32) {
+        if left == 0 {
+            let distance = self.metric.distance(base, self.target);
+            if self.beats(distance) {
+                self.best = Some(Mix {
+                    layers: self.path.clone(),
+                    color: base,
+                    distance,
+                });
+            }
+            return;
+        }
+
+        if let Some(bound) = &self.bound {
+            if !self.beats(bound.get(base, left) as f32) {
+                return;
+            }
+        }
+        if !self.visited.insert((base, left)) {
+            return;
+        }
+
+        for (i, &(p, o)) in self.palette.iter().enumerate().rev() {
+            self.path.push(i);
+            self.explore(p.over(o, base), left - 1);
+            self.path.pop();
+        }
+    }
+}
+
+fn beam_mix(target: Color, palette: &[(Color, u8)], max: u32, width: usize, metric: Metric) -> Mix {
+    let mut beam = vec![(
+        Color::WHITE,
+        Vec::new(),
+        metric.distance(Color::WHITE, target),
+    )];
+    for _ in 0..max {
+        if palette.is_empty() {
+            break;
+        }
+
+        let mut seen = HashSet::new();
+        let mut next = Vec::new();
+        for (base, layers, _) in &beam {
+            for (i, &(p, o)) in palette.iter().enumerate() {
+                let color = p.over(o, *base);
+                if seen.insert(color) {
+                    let mut layers = layers.clone();
+                    layers.push(i);
+                    next.push((color, layers, metric.distance(color, target)));
+                }
+            }
+        }
+
+        next.sort_by(|a, b| a.2.total_cmp(&b.2));
+        next.truncate(width.max(1));
+        beam = next;
+    }
+
+    let (color, layers, distance) = beam.swap_remove(0);
+    Mix {
+        layers,
+        color,
+        distance,
     }
 }
 
@@ -114,3 +725,283 @@ fn example() {
     );
     assert_eq!(color, Color::new(218, 20, 57));
 }
+
+/// The original, exhaustive search, used as a reference.
+#[cfg(test)]
+fn naive_mix(target: Color, palette: &[(Color, u8)], base: Color, max: u32) -> Color {
+    if max == 0 {
+        return base;
+    }
+
+    let mut best_so_far = Color::WHITE;
+    let mut best_distance = u32::MAX;
+    for &(p, o) in palette {
+        let candidate = naive_mix(target, palette, p.over(o, base), max - 1);
+        let dist = candidate.distance(target);
+        if dist <= best_distance {
+            best_distance = dist;
+            best_so_far = candidate;
+        }
+    }
+
+    best_so_far
+}
+
+/// Generates a palette of `len` pseudo-random colors.
+#[cfg(test)]
+fn random_palette(seed: u32, len: usize) -> Vec<(Color, u8)> {
+    let mut state = seed;
+    let mut next = move || {
+        state = state.wrapping_mul(1664525).wrapping_add(1013904223);
+        (state >> 24) as u8
+    };
+    (0..len)
+        .map(|_| (Color::new(next(), next(), next()), next()))
+        .collect()
+}
+
+#[cfg(test)]
+#[test]
+fn exact_matches_naive() {
+    for seed in 0..20 {
+        let palette = random_palette(seed, 2 + seed as usize % 4);
+        let target = random_palette(seed + 100, 1)[0].0;
+        for max in 0..5 {
+            assert_eq!(
+                target.closest_mix(&palette, max),
+                naive_mix(target, &palette, Color::WHITE, max),
+                "seed {seed}, max {max}",
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn beam_is_bounded_by_exact() {
+    let palette = random_palette(42, 6);
+    let target = Color::new(12, 200, 99);
+    let exact = target.closest_mix(&palette, 6);
+
+    let wide =
+        target.closest_mix_with(&palette, 6, Search::Beam { width: usize::MAX }, Metric::Rgb);
+    assert_eq!(wide.distance(target), exact.distance(target));
+
+    for width in [1, 5, 50] {
+        let beam = target.closest_mix_with(&palette, 6, Search::Beam { width }, Metric::Rgb);
+        assert!(beam.distance(target) >= exact.distance(target));
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn large_palette() {
+    let target = Color::new(254, 23, 102);
+    assert_eq!(
+        Search::suggested(10, 20),
+        Search::Beam {
+            width: Search::DEFAULT_WIDTH
+        }
+    );
+    assert_eq!(Search::suggested(10, 6), Search::Exact);
+    assert_eq!(Search::suggested(1, 300), Search::Exact);
+
+    for seed in 0..5 {
+        let palette = random_palette(seed, 10);
+        let start = std::time::Instant::now();
+        let mix = target.approximate_mix(&palette, 20);
+        let elapsed = start.elapsed();
+        assert!(elapsed.as_secs_f32() < 1.0, "seed {seed} took {elapsed:?}");
+        assert!(mix.distance(target) < Color::WHITE.distance(target));
+    }
+
+    let palette = random_palette(2, 10);
+    let exact = target.closest_mix_with(&palette, 8, Search::Exact, Metric::Rgb);
+    let beam = target.approximate_mix(&palette, 8);
+    assert!(exact.distance(target) <= beam.distance(target));
+
+    // Many layers of a single color, and bounds capped at `BOUND_LAYERS` layers.
+    let start = std::time::Instant::now();
+    let red = [(Color::RED, 10)];
+    assert_eq!(
+        Color::RED.closest_mix(&red, 300),
+        Color::from_layers(&red, &[0; 300])
+    );
+    let two = [(Color::RED, 10), (Color::BLUE, 10)];
+    let mix = Color::RED.closest_mix_recipe(&two, 40, Search::Exact, Metric::Rgb);
+    assert_eq!(mix.layers, [0; 40]);
+    assert!(start.elapsed().as_secs_f32() < 5.0);
+}
+
+#[cfg(test)]
+#[test]
+fn recipe() {
+    let palette = [(Color::RED, 100), (Color::GREEN, 100), (Color::BLUE, 100)];
+    let target = Color::new(254, 23, 102);
+    let mix = target.closest_mix_recipe(&palette, 5, Search::Exact, Metric::Rgb);
+    assert_eq!(mix.color, Color::new(218, 20, 57));
+    assert_eq!(mix.distance, mix.color.distance(target) as f32);
+    assert_eq!(mix.layers.len(), 5);
+    assert_eq!(Color::from_layers(&palette, &mix.layers), mix.color);
+
+    let mix = Color::RED.closest_mix_recipe(
+        &[(Color::BLUE, 10), (Color::RED, 10)],
+        1,
+        Search::Exact,
+        Metric::Rgb,
+    );
+    assert_eq!(mix.layers, [1]);
+
+    let mix = Color::RED.closest_mix_recipe(&[], 3, Search::Exact, Metric::Rgb);
+    assert_eq!(mix.layers, []);
+    assert_eq!(mix.color, Color::WHITE);
+
+    for seed in 0..10 {
+        let palette = random_palette(seed, 4);
+        let target = random_palette(seed + 100, 1)[0].0;
+        for search in [Search::Exact, Search::Beam { width: 8 }] {
+            let mix = target.closest_mix_recipe(&palette, 4, search, Metric::Rgb);
+            assert_eq!(mix.layers.len(), 4);
+            assert_eq!(Color::from_layers(&palette, &mix.layers), mix.color);
+            assert_eq!(mix.distance, mix.color.distance(target) as f32);
+        }
+    }
+}
+
+#[cfg(test)]
+fn assert_close(a: f32, b: f32) {
+    assert!((a - b).abs() < 1e-3, "{a} != {b}");
+}
+
+#[cfg(test)]
+#[test]
+fn hex() {
+    let color: Color = "#FE1766".parse().unwrap();
+    assert_eq!(color, Color::new(0xFE, 0x17, 0x66));
+    assert_eq!(color.to_string(), "#FE1766");
+    assert_eq!("#fe1766".parse(), Ok(color));
+    assert_eq!("#F0a".parse(), Ok(Color::new(0xFF, 0x00, 0xAA)));
+
+    assert_eq!("FE1766".parse::<Color>(), Err(ParseColorError::MissingHash));
+    assert_eq!(
+        "#FE176".parse::<Color>(),
+        Err(ParseColorError::InvalidLength)
+    );
+    assert_eq!(
+        "#FE1766AA".parse::<Color>(),
+        Err(ParseColorError::InvalidLength)
+    );
+    assert_eq!(
+        "#FE17G6".parse::<Color>(),
+        Err(ParseColorError::InvalidDigit(5))
+    );
+    assert_eq!("#é".parse::<Color>(), Err(ParseColorError::InvalidDigit(1)));
+}
+
+#[cfg(test)]
+#[test]
+fn hsl_hsv() {
+    let hsl = Color::new(0xFE, 0x17, 0x66).to_hsl();
+    assert_close(hsl.hue, 339.48053);
+    assert_close(hsl.saturation, 0.99147725);
+    assert_close(hsl.lightness, 0.5431373);
+
+    let hsv = Color::new(0xFE, 0x17, 0x66).to_hsv();
+    assert_close(hsv.hue, 339.48053);
+    assert_close(hsv.saturation, 0.9094488);
+    assert_close(hsv.value, 0.99607843);
+
+    let gray = Color::new(128, 128, 128);
+    assert_eq!(gray.to_hsl().saturation, 0.0);
+    assert_eq!(gray.to_hsv().saturation, 0.0);
+    assert_close(Color::GREEN.to_hsl().hue, 120.0);
+    assert_close(Color::BLUE.to_hsv().hue, 240.0);
+}
+
+#[cfg(test)]
+#[test]
+fn lab() {
+    let white = Color::WHITE.to_lab();
+    assert_close(white.l, 100.0);
+    assert!(white.a.abs() < 0.01 && white.b.abs() < 0.01);
+
+    let red = Color::RED.to_lab();
+    assert!((red.l - 53.24).abs() < 0.01);
+    assert!((red.a - 80.09).abs() < 0.01);
+    assert!((red.b - 67.20).abs() < 0.01);
+
+    let linear = Color::new(0, 128, 255).to_linear();
+    assert_eq!(linear.red, 0.0);
+    assert_close(linear.green, 0.21586);
+    assert_close(linear.blue, 1.0);
+}
+
+#[cfg(test)]
+#[test]
+fn ciede2000() {
+    // Test data from Sharma, Wu & Dalal, "The CIEDE2000 Color-Difference Formula".
+    let pairs = [
+        ((50.0, 2.6772, -79.7751), (50.0, 0.0, -82.7485), 2.0425),
+        ((50.0, 0.0, 0.0), (50.0, -1.0, 2.0), 2.3669),
+        ((50.0, 2.49, -0.001), (50.0, -2.49, 0.0011), 7.2195),
+        ((50.0, 2.5, 0.0), (73.0, 25.0, -18.0), 27.1492),
+        (
+            (60.2574, -34.0099, 36.2677),
+            (60.4626, -34.1751, 39.4387),
+            1.2644,
+        ),
+        (
+            (22.7233, 20.0904, -46.694),
+            (23.0331, 14.973, -42.5619),
+            2.0373,
+        ),
+        (
+            (90.9257, -0.5406, -0.9208),
+            (88.6381, -0.8985, -0.7239),
+            1.5381,
+        ),
+    ];
+    for ((l1, a1, b1), (l2, a2, b2), expected) in pairs {
+        let x = Lab {
+            l: l1,
+            a: a1,
+            b: b1,
+        };
+        let y = Lab {
+            l: l2,
+            a: a2,
+            b: b2,
+        };
+        assert!((x.ciede2000(y) - expected).abs() < 1e-3);
+        assert!((y.ciede2000(x) - expected).abs() < 1e-3);
+    }
+
+    assert_close(
+        Metric::Cie76.distance(Color::RED, Color::WHITE),
+        Color::RED.to_lab().cie76(Color::WHITE.to_lab()),
+    );
+    assert_eq!(Metric::Ciede2000.distance(Color::RED, Color::RED), 0.0);
+}
+
+#[cfg(test)]
+#[test]
+fn perceptual_mix() {
+    let palette = random_palette(3, 3);
+    let target = Color::new(40, 180, 200);
+
+    for metric in [Metric::Cie76, Metric::Ciede2000] {
+        let mix = target.closest_mix_recipe(&palette, 4, Search::Exact, metric);
+        assert_eq!(Color::from_layers(&palette, &mix.layers), mix.color);
+        assert_eq!(mix.distance, metric.distance(mix.color, target));
+
+        // Compare with every possible sequence of 4 layers.
+        for n in 0..3usize.pow(4) {
+            let layers = [n % 3, n / 3 % 3, n / 9 % 3, n / 27];
+            let color = Color::from_layers(&palette, &layers);
+            assert!(metric.distance(color, target) >= mix.distance);
+        }
+
+        let beam = target.closest_mix_recipe(&palette, 4, Search::Beam { width: 4 }, metric);
+        assert!(beam.distance >= mix.distance);
+    }
+}
@@ -1,50 +1,1341 @@
-#[derive(Debug)]
-enum Token<'a> {
-    Word(&'a str),
-    RedirectStdout,
+use std::env;
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{self, IsTerminal, Write};
+use std::ops::Range;
+use std::process::{self, ExitCode, Stdio};
+
+/// A piece of a word.
+#[derive(Debug, Clone, PartialEq)]
+enum WordPart {
+    /// Some text, with its quotes and escapes already removed.
+    Literal(String),
+    /// A `$NAME` or `${NAME}` variable, to be replaced by its value.
+    Variable(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    /// A word, made of the adjacent parts that compose it (`a"b"$C` is a single word).
+    Word(Word),
+    /// `<`
     RedirectStdin,
+    /// `>`
+    RedirectStdout,
+    /// `>>`
+    AppendStdout,
+    /// `2>`
+    RedirectStderr,
+    /// `2>>`
+    AppendStderr,
+    /// `&>`
+    RedirectBoth,
+    /// `|`
     Pipe,
+    /// `&&`
+    And,
+    /// `||`
+    Or,
+    /// `;`
+    Semicolon,
+    /// `&`
+    Background,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Token {
+    kind: TokenKind,
+    /// The position of the token in the input, in bytes.
+    span: Range<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum LexErrorKind {
+    /// A `'` or `"` is never closed.
+    UnterminatedQuote(char),
+    /// A `${` is never closed.
+    UnterminatedVariable,
+    /// A `${...}` does not contain a valid variable name.
+    InvalidVariable,
+    /// The input ends with a `\`.
+    TrailingBackslash,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct LexError {
+    kind: LexErrorKind,
+    /// The position of the offending character, in characters.
+    position: usize,
+}
+
+impl fmt::Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            LexErrorKind::UnterminatedQuote(q) => write!(f, "unterminated `{q}`")?,
+            LexErrorKind::UnterminatedVariable => f.write_str("unterminated `${`")?,
+            LexErrorKind::InvalidVariable => f.write_str("invalid variable name")?,
+            LexErrorKind::TrailingBackslash => f.write_str("trailing `\\`")?,
+        }
+        write!(f, " at column {}", self.position + 1)
+    }
+}
+
+/// Whether `c` ends an unquoted word.
+fn is_special(c: char) -> bool {
+    c.is_whitespace() || matches!(c, '|' | '&' | ';' | '<' | '>')
+}
+
+fn is_name_start(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_'
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+/// Splits a command-line into [`Token`]s.
+struct Lexer<'a> {
+    input: &'a str,
+    position: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(input: &'a str) -> Self {
+        Self { input, position: 0 }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.input[self.position..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    /// Returns an error for the character at byte `position`.
+    fn error(&self, kind: LexErrorKind, position: usize) -> LexError {
+        LexError {
+            kind,
+            position: self.input[..position].chars().count(),
+        }
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.position += c.len_utf8();
+        Some(c)
+    }
+
+    /// Reads an operator, if the input starts with one.
+    fn operator(&mut self) -> Option<TokenKind> {
+        // Longer operators come first, so that `>>` is not read as two `>`.
+        const OPERATORS: [(&str, TokenKind); 11] = [
+            ("2>>", TokenKind::AppendStderr),
+            (">>", TokenKind::AppendStdout),
+            ("2>", TokenKind::RedirectStderr),
+            ("&>", TokenKind::RedirectBoth),
+            ("&&", TokenKind::And),
+            ("||", TokenKind::Or),
+            (">", TokenKind::RedirectStdout),
+            ("<", TokenKind::RedirectStdin),
+            ("|", TokenKind::Pipe),
+            (";", TokenKind::Semicolon),
+            ("&", TokenKind::Background),
+        ];
+
+        for (op, kind) in &OPERATORS {
+            if self.rest().starts_with(op) {
+                self.position += op.len();
+                return Some(kind.clone());
+            }
+        }
+        None
+    }
+
+    /// Reads the variable following a `$`, which has already been consumed. When the `$` is
+    /// not followed by a variable name, it is a literal `$`.
+    fn variable(&mut self, dollar: usize) -> Result<Option<String>, LexError> {
+        if self.peek() == Some('{') {
+            self.bump();
+            let start = self.position;
+            let len = match self.rest().find('}') {
+                Some(len) => len,
+                None => return Err(self.error(LexErrorKind::UnterminatedVariable, dollar)),
+            };
+            let name = &self.rest()[..len];
+            let valid =
+                name == "?" || name.starts_with(is_name_start) && name.chars().all(is_name_char);
+            if !valid {
+                return Err(self.error(LexErrorKind::InvalidVariable, start));
+            }
+            self.position += len + 1;
+            return Ok(Some(name.to_string()));
+        }
+
+        if self.peek() == Some('?') {
+            self.bump();
+            return Ok(Some("?".to_string()));
+        }
+
+        if !self.rest().starts_with(is_name_start) {
+            return Ok(None);
+        }
+        let len = self
+            .rest()
+            .find(|c| !is_name_char(c))
+            .unwrap_or(self.rest().len());
+        let name = &self.rest()[..len];
+        self.position += len;
+        Ok(Some(name.to_string()))
+    }
+
+    /// Reads a word, which may be made of several quoted or unquoted parts.
+    fn word(&mut self) -> Result<Vec<WordPart>, LexError> {
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let push_variable = |parts: &mut Vec<WordPart>, literal: &mut String, name| {
+            if !literal.is_empty() {
+                parts.push(WordPart::Literal(std::mem::take(literal)));
+            }
+            parts.push(WordPart::Variable(name));
+        };
+
+        while let Some(c) = self.peek() {
+            if is_special(c) {
+                break;
+            }
+
+            let start = self.position;
+            self.bump();
+            match c {
+                '\\' => match self.bump() {
+                    // An escaped newline joins two lines.
+                    Some('\n') => (),
+                    Some(c) => literal.push(c),
+                    None => return Err(self.error(LexErrorKind::TrailingBackslash, start)),
+                },
+                '\'' => match self.rest().find('\'') {
+                    Some(len) => {
+                        literal.push_str(&self.rest()[..len]);
+                        self.position += len + 1;
+                    }
+                    None => return Err(self.error(LexErrorKind::UnterminatedQuote('\''), start)),
+                },
+                '"' => loop {
+                    let Some(c) = self.bump() else {
+                        return Err(self.error(LexErrorKind::UnterminatedQuote('"'), start));
+                    };
+                    match c {
+                        '"' => break,
+                        '\\' => match self.peek() {
+                            Some('\n') => {
+                                self.bump();
+                            }
+                            Some(c @ ('$' | '"' | '\\' | '`')) => {
+                                self.bump();
+                                literal.push(c);
+                            }
+                            _ => literal.push('\\'),
+                        },
+                        '$' => {
+                            let dollar = self.position - 1;
+                            match self.variable(dollar)? {
+                                Some(name) => push_variable(&mut parts, &mut literal, name),
+                                None => literal.push('$'),
+                            }
+                        }
+                        c => literal.push(c),
+                    }
+                },
+                '$' => match self.variable(start)? {
+                    Some(name) => push_variable(&mut parts, &mut literal, name),
+                    None => literal.push('$'),
+                },
+                c => literal.push(c),
+            }
+        }
+
+        // Keep a part for empty quoted strings, such as `''`, so that they are still words.
+        if !literal.is_empty() || parts.is_empty() {
+            parts.push(WordPart::Literal(literal));
+        }
+        Ok(parts)
+    }
+}
+
+impl Iterator for Lexer<'_> {
+    type Item = Result<Token, LexError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let rest = self.rest();
+        self.position += rest.len() - rest.trim_start().len();
+        if self.rest().is_empty() {
+            return None;
+        }
+
+        let start = self.position;
+        let kind = match self.operator() {
+            Some(kind) => kind,
+            None => match self.word() {
+                Ok(parts) => TokenKind::Word(parts),
+                Err(err) => {
+                    // Stop at the first error.
+                    self.position = self.input.len();
+                    return Some(Err(err));
+                }
+            },
+        };
+
+        Some(Ok(Token {
+            kind,
+            span: start..self.position,
+        }))
+    }
+}
+
+/// Splits `input` into tokens, stopping at the first error.
+fn tokenize(input: &str) -> Result<Vec<Token>, LexError> {
+    Lexer::new(input).collect()
+}
+
+impl fmt::Display for WordPart {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Literal(s) => write!(f, "{s:?}"),
+            Self::Variable(name) => write!(f, "${{{name}}}"),
+        }
+    }
+}
+
+impl TokenKind {
+    /// Returns how the token is written, or `word` for words.
+    fn symbol(&self) -> &'static str {
+        match self {
+            Self::Word(_) => "word",
+            Self::RedirectStdin => "<",
+            Self::RedirectStdout => ">",
+            Self::AppendStdout => ">>",
+            Self::RedirectStderr => "2>",
+            Self::AppendStderr => "2>>",
+            Self::RedirectBoth => "&>",
+            Self::Pipe => "|",
+            Self::And => "&&",
+            Self::Or => "||",
+            Self::Semicolon => ";",
+            Self::Background => "&",
+        }
+    }
+}
+
+impl fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Word(parts) => {
+                f.write_str("Word(")?;
+                for (i, part) in parts.iter().enumerate() {
+                    if i != 0 {
+                        f.write_str(" ")?;
+                    }
+                    write!(f, "{part}")?;
+                }
+                f.write_str(")")
+            }
+            Self::RedirectStdin => f.write_str("RedirectStdin"),
+            Self::RedirectStdout => f.write_str("RedirectStdout"),
+            Self::AppendStdout => f.write_str("AppendStdout"),
+            Self::RedirectStderr => f.write_str("RedirectStderr"),
+            Self::AppendStderr => f.write_str("AppendStderr"),
+            Self::RedirectBoth => f.write_str("RedirectBoth"),
+            Self::Pipe => f.write_str("Pipe"),
+            Self::And => f.write_str("And"),
+            Self::Or => f.write_str("Or"),
+            Self::Semicolon => f.write_str("Semicolon"),
+            Self::Background => f.write_str("Background"),
+        }
+    }
+}
+
+/// A word of the command-line, as a sequence of literal and variable parts.
+type Word = Vec<WordPart>;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum RedirectKind {
+    /// `< file`
+    Stdin,
+    /// `> file`
+    Stdout,
+    /// `>> file`
+    AppendStdout,
+    /// `2> file`
+    Stderr,
+    /// `2>> file`
+    AppendStderr,
+    /// `&> file`
+    Both,
 }
 
-fn next_token<'a>(s: &mut &'a str) -> Option<Token<'a>> {
-    *s = s.trim_start();
-    if s.is_empty() {
-        return None;
+impl RedirectKind {
+    fn from_token(kind: &TokenKind) -> Option<Self> {
+        match kind {
+            TokenKind::RedirectStdin => Some(Self::Stdin),
+            TokenKind::RedirectStdout => Some(Self::Stdout),
+            TokenKind::AppendStdout => Some(Self::AppendStdout),
+            TokenKind::RedirectStderr => Some(Self::Stderr),
+            TokenKind::AppendStderr => Some(Self::AppendStderr),
+            TokenKind::RedirectBoth => Some(Self::Both),
+            _ => None,
+        }
     }
-    if let Some(rem) = s.strip_prefix('>') {
-        *s = rem;
-        Some(Token::RedirectStdout)
-    } else if let Some(rem) = s.strip_prefix('<') {
-        *s = rem;
-        Some(Token::RedirectStdin)
-    } else if let Some(rem) = s.strip_prefix('|') {
-        *s = rem;
-        Some(Token::Pipe)
-    } else {
-        for (i, c) in s.char_indices() {
-            if c.is_whitespace() || c == '|' || c == '<' || c == '>' {
-                let word;
-                (word, *s) = s.split_at(i);
-                return Some(Token::Word(word));
+
+    fn symbol(self) -> &'static str {
+        match self {
+            Self::Stdin => "<",
+            Self::Stdout => ">",
+            Self::AppendStdout => ">>",
+            Self::Stderr => "2>",
+            Self::AppendStderr => "2>>",
+            Self::Both => "&>",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Redirect {
+    kind: RedirectKind,
+    target: Word,
+}
+
+/// A single program invocation, such as `grep -v foo < in.txt`.
+#[derive(Debug, Clone, PartialEq)]
+struct Command {
+    argv: Vec<Word>,
+    redirects: Vec<Redirect>,
+}
+
+/// Commands whose standard output is connected to the standard input of the next one.
+#[derive(Debug, Clone, PartialEq)]
+struct Pipeline {
+    commands: Vec<Command>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Connector {
+    /// `&&`, only runs the next pipeline when the previous one succeeds.
+    And,
+    /// `||`, only runs the next pipeline when the previous one fails.
+    Or,
+}
+
+/// A sequence of pipelines joined by `&&` or `||`, terminated by `;` or `&`.
+#[derive(Debug, Clone, PartialEq)]
+struct Job {
+    first: Pipeline,
+    rest: Vec<(Connector, Pipeline)>,
+    /// Whether the job ends with `&`.
+    background: bool,
+}
+
+/// A whole command-line.
+#[derive(Debug, Clone, PartialEq)]
+struct CommandLine {
+    jobs: Vec<Job>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum ParseError {
+    /// A token appears where it is not allowed. The position is in characters.
+    Unexpected { token: TokenKind, position: usize },
+    /// The command-line ends in the middle of a command.
+    UnexpectedEnd,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unexpected { token, position } => {
+                write!(
+                    f,
+                    "unexpected `{}` at column {}",
+                    token.symbol(),
+                    position + 1
+                )
             }
+            Self::UnexpectedEnd => f.write_str("unexpected end of line"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum SyntaxError {
+    Lex(LexError),
+    Parse(ParseError),
+}
+
+impl fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Lex(err) => err.fmt(f),
+            Self::Parse(err) => err.fmt(f),
         }
-        let word = *s;
-        *s = "";
-        Some(Token::Word(word))
     }
 }
 
-fn print_all_tokens(mut s: &str) {
-    while let Some(token) = next_token(&mut s) {
-        println!("{token:?}");
+/// Builds a [`CommandLine`] from a list of tokens.
+struct Parser<'a> {
+    /// The parsed command-line, to report positions in characters.
+    input: &'a str,
+    tokens: std::iter::Peekable<std::vec::IntoIter<Token>>,
+}
+
+impl Parser<'_> {
+    fn peek(&mut self) -> Option<&TokenKind> {
+        self.tokens.peek().map(|token| &token.kind)
+    }
+
+    /// Returns an error for `token`.
+    fn unexpected_token(&self, token: Token) -> ParseError {
+        ParseError::Unexpected {
+            token: token.kind,
+            position: self.input[..token.span.start].chars().count(),
+        }
+    }
+
+    /// Returns an error for the next token.
+    fn unexpected(&mut self) -> ParseError {
+        match self.tokens.next() {
+            Some(token) => self.unexpected_token(token),
+            None => ParseError::UnexpectedEnd,
+        }
+    }
+
+    fn command_line(&mut self) -> Result<CommandLine, ParseError> {
+        let mut jobs = Vec::new();
+
+        while self.peek().is_some() {
+            let mut job = self.job()?;
+            match self.peek() {
+                Some(TokenKind::Semicolon) => (),
+                Some(TokenKind::Background) => job.background = true,
+                None => {
+                    jobs.push(job);
+                    break;
+                }
+                Some(_) => return Err(self.unexpected()),
+            }
+            self.tokens.next();
+            jobs.push(job);
+        }
+
+        Ok(CommandLine { jobs })
+    }
+
+    fn job(&mut self) -> Result<Job, ParseError> {
+        let first = self.pipeline()?;
+        let mut rest = Vec::new();
+
+        loop {
+            let connector = match self.peek() {
+                Some(TokenKind::And) => Connector::And,
+                Some(TokenKind::Or) => Connector::Or,
+                _ => break,
+            };
+            self.tokens.next();
+            rest.push((connector, self.pipeline()?));
+        }
+
+        Ok(Job {
+            first,
+            rest,
+            background: false,
+        })
+    }
+
+    fn pipeline(&mut self) -> Result<Pipeline, ParseError> {
+        let mut commands = vec![self.command()?];
+        while self.peek() == Some(&TokenKind::Pipe) {
+            self.tokens.next();
+            commands.push(self.command()?);
+        }
+        Ok(Pipeline { commands })
+    }
+
+    fn command(&mut self) -> Result<Command, ParseError> {
+        let mut argv = Vec::new();
+        let mut redirects = Vec::new();
+
+        loop {
+            match self.peek() {
+                Some(TokenKind::Word(_)) => {
+                    if let Some(Token {
+                        kind: TokenKind::Word(word),
+                        ..
+                    }) = self.tokens.next()
+                    {
+                        argv.push(word);
+                    }
+                }
+                Some(kind) => {
+                    let Some(kind) = RedirectKind::from_token(kind) else {
+                        break;
+                    };
+                    self.tokens.next();
+                    match self.tokens.next() {
+                        Some(Token {
+                            kind: TokenKind::Word(target),
+                            ..
+                        }) => redirects.push(Redirect { kind, target }),
+                        Some(token) => return Err(self.unexpected_token(token)),
+                        None => return Err(ParseError::UnexpectedEnd),
+                    }
+                }
+                None => break,
+            }
+        }
+
+        if argv.is_empty() && redirects.is_empty() {
+            return Err(self.unexpected());
+        }
+
+        Ok(Command { argv, redirects })
     }
 }
 
-fn main() {
-    if ftkit::ARGS.len() != 2 {
-        eprintln!("error: exactly one argument expected");
-        return;
+/// Parses a whole command-line.
+fn parse(input: &str) -> Result<CommandLine, SyntaxError> {
+    let tokens = tokenize(input).map_err(SyntaxError::Lex)?;
+    let mut parser = Parser {
+        input,
+        tokens: tokens.into_iter().peekable(),
+    };
+    parser.command_line().map_err(SyntaxError::Parse)
+}
+
+/// Writes a word as its quoted literal parts and `${NAME}` variables.
+fn write_word(f: &mut fmt::Formatter<'_>, word: &Word) -> fmt::Result {
+    for part in word {
+        write!(f, "{part}")?;
     }
+    Ok(())
+}
+
+fn write_pipeline(f: &mut fmt::Formatter<'_>, pipeline: &Pipeline) -> fmt::Result {
+    writeln!(f, "  pipeline")?;
+    for command in &pipeline.commands {
+        writeln!(f, "    command")?;
+        for arg in &command.argv {
+            f.write_str("      arg ")?;
+            write_word(f, arg)?;
+            writeln!(f)?;
+        }
+        for redirect in &command.redirects {
+            write!(f, "      {} ", redirect.kind.symbol())?;
+            write_word(f, &redirect.target)?;
+            writeln!(f)?;
+        }
+    }
+    Ok(())
+}
+
+/// Pretty-prints the command-line as a tree.
+impl fmt::Display for CommandLine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for job in &self.jobs {
+            if job.background {
+                writeln!(f, "job &")?;
+            } else {
+                writeln!(f, "job")?;
+            }
+            write_pipeline(f, &job.first)?;
+            for (connector, pipeline) in &job.rest {
+                match connector {
+                    Connector::And => writeln!(f, "  &&")?,
+                    Connector::Or => writeln!(f, "  ||")?,
+                }
+                write_pipeline(f, pipeline)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The exit status of a command that could not be found.
+const NOT_FOUND: i32 = 127;
+/// The exit status of a command that could not be started.
+const NOT_EXECUTABLE: i32 = 126;
+/// The exit status of a command-line with a syntax error.
+const SYNTAX_ERROR: i32 = 2;
+
+/// The commands that are run by the shell itself, as they change its own state.
+const BUILTINS: [&str; 3] = ["cd", "exit", "export"];
+
+/// Converts the status of a finished process into a shell exit status.
+fn exit_status(status: process::ExitStatus) -> i32 {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        if let Some(signal) = status.signal() {
+            return 128 + signal;
+        }
+    }
+    status.code().unwrap_or(1)
+}
+
+/// Where the standard streams of a command go, once its redirections are applied.
+#[derive(Default)]
+struct Streams {
+    stdin: Option<File>,
+    stdout: Option<File>,
+    stderr: Option<File>,
+}
+
+/// The state of the shell that outlives a single command-line.
+#[derive(Default)]
+struct Shell {
+    /// The exit status of the last pipeline, as reported by `$?`.
+    status: i32,
+    /// Set by the `exit` builtin to the status the shell should exit with.
+    exit: Option<i32>,
+    /// Jobs started with `&` which have not finished yet.
+    background: Vec<process::Child>,
+}
+
+impl Shell {
+    /// Replaces the variables of a word by their value.
+    ///
+    /// Unset variables expand to nothing. Unlike a POSIX shell, the value of a variable is never
+    /// split into several words.
+    fn expand(&self, word: &Word) -> String {
+        let mut result = String::new();
+        for part in word {
+            match part {
+                WordPart::Literal(text) => result.push_str(text),
+                WordPart::Variable(name) if name == "?" => {
+                    result.push_str(&self.status.to_string())
+                }
+                WordPart::Variable(name) => result.push_str(&env::var(name).unwrap_or_default()),
+            }
+        }
+        result
+    }
+
+    /// Opens the files of the redirections of `command`.
+    ///
+    /// When a stream is redirected several times, the last redirection wins, but every file is
+    /// still opened (and created).
+    fn open_redirects(&self, command: &Command) -> io::Result<Streams> {
+        let mut streams = Streams::default();
+        for redirect in &command.redirects {
+            let path = self.expand(&redirect.target);
+            let open = |result: io::Result<File>| {
+                result.map_err(|err| io::Error::new(err.kind(), format!("{path}: {err}")))
+            };
+            match redirect.kind {
+                RedirectKind::Stdin => streams.stdin = Some(open(File::open(&path))?),
+                RedirectKind::Stdout => streams.stdout = Some(open(File::create(&path))?),
+                RedirectKind::AppendStdout => {
+                    let file = OpenOptions::new().append(true).create(true).open(&path);
+                    streams.stdout = Some(open(file)?);
+                }
+                RedirectKind::Stderr => streams.stderr = Some(open(File::create(&path))?),
+                RedirectKind::AppendStderr => {
+                    let file = OpenOptions::new().append(true).create(true).open(&path);
+                    streams.stderr = Some(open(file)?);
+                }
+                RedirectKind::Both => {
+                    let file = open(File::create(&path))?;
+                    streams.stderr = Some(file.try_clone()?);
+                    streams.stdout = Some(file);
+                }
+            }
+        }
+        Ok(streams)
+    }
+
+    /// Parses and runs a command-line.
+    fn execute(&mut self, input: &str) {
+        match parse(input) {
+            Ok(line) => self.run(&line),
+            Err(err) => {
+                eprintln!("m02ex06: {err}");
+                self.status = SYNTAX_ERROR;
+            }
+        }
+    }
+
+    /// Runs a whole command-line, stopping early if `exit` is called.
+    fn run(&mut self, line: &CommandLine) {
+        for job in &line.jobs {
+            if self.exit.is_some() {
+                break;
+            }
+            if job.background {
+                self.spawn_background(job);
+            } else {
+                self.run_job(job);
+            }
+        }
+    }
+
+    fn run_job(&mut self, job: &Job) {
+        self.status = self.run_pipeline(&job.first);
+        for (connector, pipeline) in &job.rest {
+            if self.exit.is_some() {
+                break;
+            }
+            let run = match connector {
+                Connector::And => self.status == 0,
+                Connector::Or => self.status != 0,
+            };
+            if run {
+                self.status = self.run_pipeline(pipeline);
+            }
+        }
+    }
+
+    /// Starts the pipelines of a `&` job without waiting for them.
+    ///
+    /// Only the first pipeline of the job is started right away, as the ones after `&&` or `||`
+    /// would depend on its exit status.
+    fn spawn_background(&mut self, job: &Job) {
+        if !job.rest.is_empty() {
+            eprintln!("m02ex06: `&&` and `||` are not supported in background jobs");
+            self.status = 1;
+            return;
+        }
+        match self.spawn_pipeline(&job.first) {
+            Ok(children) => {
+                if let Some(last) = children.last() {
+                    eprintln!("[{}]", last.id());
+                }
+                self.background.extend(children);
+                self.status = 0;
+            }
+            Err(status) => self.status = status,
+        }
+    }
+
+    /// Forgets about the background jobs which have finished.
+    fn reap(&mut self) {
+        self.background
+            .retain_mut(|child| matches!(child.try_wait(), Ok(None)));
+    }
+
+    /// Runs a pipeline and returns its exit status, which is the one of its last command.
+    fn run_pipeline(&mut self, pipeline: &Pipeline) -> i32 {
+        if let [command] = pipeline.commands.as_slice() {
+            if let Some(status) = self.run_builtin(command) {
+                return status;
+            }
+        }
+
+        match self.spawn_pipeline(pipeline) {
+            Ok(children) => {
+                let count = children.len();
+                let mut status = 0;
+                for (i, mut child) in children.into_iter().enumerate() {
+                    let result = child.wait();
+                    if i + 1 == count {
+                        status = result.map_or(1, exit_status);
+                    }
+                }
+                status
+            }
+            Err(status) => status,
+        }
+    }
+
+    /// Runs `command` if it is a builtin, and returns its exit status.
+    fn run_builtin(&mut self, command: &Command) -> Option<i32> {
+        let argv: Vec<String> = command.argv.iter().map(|w| self.expand(w)).collect();
+        let (name, args) = argv.split_first()?;
+        if !BUILTINS.contains(&name.as_str()) {
+            return None;
+        }
+
+        let streams = match self.open_redirects(command) {
+            Ok(streams) => streams,
+            Err(err) => {
+                eprintln!("m02ex06: {err}");
+                return Some(1);
+            }
+        };
+        let mut stdout: Box<dyn Write> = match streams.stdout {
+            Some(file) => Box::new(file),
+            None => Box::new(io::stdout()),
+        };
+        let mut stderr: Box<dyn Write> = match streams.stderr {
+            Some(file) => Box::new(file),
+            None => Box::new(io::stderr()),
+        };
+
+        let result = match name.as_str() {
+            "cd" => cd(args),
+            "exit" => self.exit(args),
+            _ => export(args, &mut stdout),
+        };
+        match result {
+            Ok(()) => Some(0),
+            Err(err) => {
+                let _ = writeln!(stderr, "m02ex06: {name}: {err}");
+                Some(1)
+            }
+        }
+    }
+
+    /// The `exit [status]` builtin. Without an argument, the status of the last pipeline is used.
+    fn exit(&mut self, args: &[String]) -> Result<(), String> {
+        match args {
+            [] => self.exit = Some(self.status),
+            [status] => match status.parse::<i32>() {
+                Ok(status) => self.exit = Some(status.rem_euclid(256)),
+                Err(_) => {
+                    self.exit = Some(SYNTAX_ERROR);
+                    return Err(format!("{status}: numeric argument required"));
+                }
+            },
+            _ => return Err("too many arguments".to_string()),
+        }
+        Ok(())
+    }
+
+    /// Starts every command of a pipeline, connecting each one to the next with a pipe.
+    ///
+    /// When a command cannot be started, the ones which already were are waited for and the
+    /// exit status to report is returned instead.
+    fn spawn_pipeline(&self, pipeline: &Pipeline) -> Result<Vec<process::Child>, i32> {
+        let mut children: Vec<process::Child> = Vec::new();
+        let mut previous: Option<process::ChildStdout> = None;
+        let mut failure = None;
+
+        for (i, command) in pipeline.commands.iter().enumerate() {
+            let is_last = i + 1 == pipeline.commands.len();
+            let stdin = match previous.take() {
+                Some(pipe) => Stdio::from(pipe),
+                None if i == 0 => Stdio::inherit(),
+                // The previous command did not start, or wrote its output to a file.
+                None => Stdio::null(),
+            };
+            let stdout = if is_last {
+                Stdio::inherit()
+            } else {
+                Stdio::piped()
+            };
+            match self.spawn(command, stdin, stdout) {
+                Ok(Some(mut child)) => {
+                    previous = child.stdout.take();
+                    children.push(child);
+                }
+                Ok(None) => (),
+                Err(status) => {
+                    if is_last {
+                        failure = Some(status);
+                    }
+                }
+            }
+        }
+
+        match failure {
+            Some(status) => {
+                for mut child in children {
+                    let _ = child.wait();
+                }
+                Err(status)
+            }
+            None => Ok(children),
+        }
+    }
+
+    /// Starts a single command of a pipeline.
+    ///
+    /// Returns `None` for a command made only of redirections, which are applied but run
+    /// nothing.
+    fn spawn(
+        &self,
+        command: &Command,
+        stdin: Stdio,
+        stdout: Stdio,
+    ) -> Result<Option<process::Child>, i32> {
+        let streams = self.open_redirects(command).map_err(|err| {
+            eprintln!("m02ex06: {err}");
+            1
+        })?;
+
+        let argv: Vec<String> = command.argv.iter().map(|w| self.expand(w)).collect();
+        let Some((program, args)) = argv.split_first() else {
+            return Ok(None);
+        };
+        if BUILTINS.contains(&program.as_str()) {
+            eprintln!("m02ex06: {program}: builtins cannot be used in pipelines");
+            return Err(1);
+        }
+
+        let mut process = process::Command::new(program);
+        process.args(args);
+        process.stdin(streams.stdin.map_or(stdin, Stdio::from));
+        process.stdout(streams.stdout.map_or(stdout, Stdio::from));
+        if let Some(file) = streams.stderr {
+            process.stderr(file);
+        }
+
+        process.spawn().map(Some).map_err(|err| {
+            if err.kind() == io::ErrorKind::NotFound {
+                eprintln!("m02ex06: {program}: command not found");
+                NOT_FOUND
+            } else {
+                eprintln!("m02ex06: {program}: {err}");
+                NOT_EXECUTABLE
+            }
+        })
+    }
+}
+
+/// The `cd [directory]` builtin. Without an argument, goes to `$HOME`.
+fn cd(args: &[String]) -> Result<(), String> {
+    let dir = match args {
+        [] => env::var("HOME").map_err(|_| "HOME not set".to_string())?,
+        [dir] => dir.clone(),
+        _ => return Err("too many arguments".to_string()),
+    };
+    env::set_current_dir(&dir).map_err(|err| format!("{dir}: {err}"))?;
+    if let Ok(cwd) = env::current_dir() {
+        env::set_var("PWD", cwd);
+    }
+    Ok(())
+}
+
+/// The `export [NAME=value]...` builtin. Without an argument, lists the environment.
+fn export(args: &[String], stdout: &mut dyn Write) -> Result<(), String> {
+    if args.is_empty() {
+        let mut vars: Vec<_> = env::vars_os().collect();
+        vars.sort();
+        for (name, value) in vars {
+            let _ = writeln!(
+                stdout,
+                "export {}={:?}",
+                name.to_string_lossy(),
+                value.to_string_lossy()
+            );
+        }
+        return Ok(());
+    }
+
+    for arg in args {
+        let (name, value) = match arg.split_once('=') {
+            Some((name, value)) => (name, Some(value)),
+            None => (arg.as_str(), None),
+        };
+        let mut chars = name.chars();
+        if !chars.next().is_some_and(is_name_start) || !chars.all(is_name_char) {
+            return Err(format!("`{arg}`: not a valid identifier"));
+        }
+        // Every variable of this shell is already exported, so `export NAME` does nothing.
+        if let Some(value) = value {
+            env::set_var(name, value);
+        }
+    }
+    Ok(())
+}
+
+/// Reads command-lines from the standard input until `exit` or the end of input, and returns
+/// the status to exit with.
+fn repl() -> i32 {
+    let mut shell = Shell::default();
+    let interactive = io::stdin().is_terminal();
+
+    loop {
+        shell.reap();
+        if interactive {
+            eprint!("$ ");
+        }
+        let mut line = ftkit::read_line();
+        if line.is_empty() {
+            break;
+        }
+        // Quoted strings may span several lines.
+        while let Err(SyntaxError::Lex(LexError {
+            kind: LexErrorKind::UnterminatedQuote(_),
+            ..
+        })) = parse(&line)
+        {
+            if interactive {
+                eprint!("> ");
+            }
+            let next = ftkit::read_line();
+            if next.is_empty() {
+                break;
+            }
+            line.push_str(&next);
+        }
+
+        shell.execute(&line);
+        if let Some(status) = shell.exit {
+            return status;
+        }
+    }
+
+    shell.status
+}
+
+fn print_all_tokens(s: &str) -> i32 {
+    match tokenize(s) {
+        Ok(tokens) => {
+            for token in tokens {
+                println!(
+                    "{:>3}..{:<3} {}",
+                    token.span.start, token.span.end, token.kind
+                );
+            }
+            0
+        }
+        Err(err) => {
+            eprintln!("error: {err}");
+            SYNTAX_ERROR
+        }
+    }
+}
+
+fn print_ast(s: &str) -> i32 {
+    match parse(s) {
+        Ok(line) => {
+            print!("{line}");
+            0
+        }
+        Err(err) => {
+            eprintln!("error: {err}");
+            SYNTAX_ERROR
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let status = match ftkit::ARGS.len() {
+        1 => repl(),
+        2 => print_all_tokens(&ftkit::ARGS[1]),
+        3 if &ftkit::ARGS[1] == "--ast" => print_ast(&ftkit::ARGS[2]),
+        3 if &ftkit::ARGS[1] == "-c" => {
+            let mut shell = Shell::default();
+            shell.execute(&ftkit::ARGS[2]);
+            shell.exit.unwrap_or(shell.status)
+        }
+        _ => {
+            eprintln!("usage: {} [--ast | -c] [command-line]", &ftkit::ARGS[0]);
+            SYNTAX_ERROR
+        }
+    };
+    ExitCode::from(status as u8)
+}
+
+#[cfg(test)]
+fn kinds(input: &str) -> Vec<TokenKind> {
+    tokenize(input)
+        .unwrap()
+        .into_iter()
+        .map(|token| token.kind)
+        .collect()
+}
+
+#[cfg(test)]
+fn word(parts: &[&str]) -> TokenKind {
+    TokenKind::Word(
+        parts
+            .iter()
+            .map(
+                |part| match part.strip_prefix("${").and_then(|p| p.strip_suffix('}')) {
+                    Some(name) => WordPart::Variable(name.to_string()),
+                    None => WordPart::Literal(part.to_string()),
+                },
+            )
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+#[test]
+fn operators() {
+    use TokenKind::*;
+
+    assert_eq!(
+        kinds("a|b||c&d&&e;f<g>h>>i 2>j&>k 2>>l"),
+        [
+            word(&["a"]),
+            Pipe,
+            word(&["b"]),
+            Or,
+            word(&["c"]),
+            Background,
+            word(&["d"]),
+            And,
+            word(&["e"]),
+            Semicolon,
+            word(&["f"]),
+            RedirectStdin,
+            word(&["g"]),
+            RedirectStdout,
+            word(&["h"]),
+            AppendStdout,
+            word(&["i"]),
+            RedirectStderr,
+            word(&["j"]),
+            RedirectBoth,
+            word(&["k"]),
+            AppendStderr,
+            word(&["l"]),
+        ]
+    );
+
+    // `2>` is only an operator at the start of a word.
+    assert_eq!(kinds("a2>b"), [word(&["a2"]), RedirectStdout, word(&["b"])]);
+    assert_eq!(kinds("  \t "), []);
+}
+
+#[cfg(test)]
+#[test]
+fn quotes_and_escapes() {
+    assert_eq!(kinds(r#"'a b' "c d""#), [word(&["a b"]), word(&["c d"])]);
+    assert_eq!(kinds(r#"x'y'"z"w"#), [word(&["xyzw"])]);
+    assert_eq!(
+        kinds(r#"'$HOME \n' "\$ \" \\ \n""#),
+        [word(&["$HOME \\n"]), word(&["$ \" \\ \\n"])]
+    );
+    assert_eq!(kinds(r"a\ b\|c\\"), [word(&["a b|c\\"])]);
+    assert_eq!(kinds("a\\\nb"), [word(&["ab"])]);
+    assert_eq!(kinds("'' \"\""), [word(&[""]), word(&[""])]);
+    assert_eq!(kinds("'|'"), [word(&["|"])]);
+}
+
+#[cfg(test)]
+#[test]
+fn variables() {
+    assert_eq!(kinds("$HOME"), [word(&["${HOME}"])]);
+    assert_eq!(
+        kinds("a${B}c$D_1.e"),
+        [word(&["a", "${B}", "c", "${D_1}", ".e"])]
+    );
+    assert_eq!(
+        kinds(r#""x $Y z" '$Y'"#),
+        [word(&["x ", "${Y}", " z"]), word(&["$Y"])]
+    );
+    assert_eq!(kinds("$? ${?}"), [word(&["${?}"]), word(&["${?}"])]);
+    assert_eq!(
+        kinds("$ $1 \\$A"),
+        [word(&["$"]), word(&["$1"]), word(&["$A"])]
+    );
+}
+
+#[cfg(test)]
+#[test]
+fn spans() {
+    let spans: Vec<_> = tokenize("ls  'a b'>>out 2>err&")
+        .unwrap()
+        .into_iter()
+        .map(|token| token.span)
+        .collect();
+    assert_eq!(spans, [0..2, 4..9, 9..11, 11..14, 15..17, 17..20, 20..21]);
+}
+
+#[cfg(test)]
+#[test]
+fn errors() {
+    let error = |kind, position| Err(LexError { kind, position });
+
+    assert_eq!(
+        tokenize("echo 'abc"),
+        error(LexErrorKind::UnterminatedQuote('\''), 5)
+    );
+    assert_eq!(
+        tokenize("echo a\"b'c"),
+        error(LexErrorKind::UnterminatedQuote('"'), 6)
+    );
+    assert_eq!(
+        tokenize("echo ${A"),
+        error(LexErrorKind::UnterminatedVariable, 5)
+    );
+    assert_eq!(tokenize("${1x}"), error(LexErrorKind::InvalidVariable, 2));
+    assert_eq!(tokenize("a \\"), error(LexErrorKind::TrailingBackslash, 2));
+    assert_eq!(
+        tokenize("'é").unwrap_err().to_string(),
+        "unterminated `'` at column 1"
+    );
+    // Columns count characters, not bytes.
+    assert_eq!(
+        tokenize("échec 'a").unwrap_err().to_string(),
+        "unterminated `'` at column 7"
+    );
+    assert_eq!(
+        tokenize("« ${é} »"),
+        error(LexErrorKind::InvalidVariable, 4)
+    );
+}
+
+#[cfg(test)]
+#[test]
+fn parse_structure() {
+    let line = parse("cat < in | grep -v \"$X\" 2> err && echo ok || echo ko; sleep 1 &").unwrap();
+    assert_eq!(
+        line.to_string(),
+        "\
+job
+  pipeline
+    command
+      arg \"cat\"
+      < \"in\"
+    command
+      arg \"grep\"
+      arg \"-v\"
+      arg ${X}
+      2> \"err\"
+  &&
+  pipeline
+    command
+      arg \"echo\"
+      arg \"ok\"
+  ||
+  pipeline
+    command
+      arg \"echo\"
+      arg \"ko\"
+job &
+  pipeline
+    command
+      arg \"sleep\"
+      arg \"1\"
+"
+    );
+
+    assert_eq!(parse("").unwrap().jobs, []);
+    assert_eq!(parse("a; b;").unwrap().jobs.len(), 2);
+
+    let line = parse("> out &> both >> app 2>> log").unwrap();
+    let command = &line.jobs[0].first.commands[0];
+    assert!(command.argv.is_empty());
+    let kinds: Vec<_> = command.redirects.iter().map(|r| r.kind).collect();
+    assert_eq!(
+        kinds,
+        [
+            RedirectKind::Stdout,
+            RedirectKind::Both,
+            RedirectKind::AppendStdout,
+            RedirectKind::AppendStderr
+        ]
+    );
+}
+
+#[cfg(test)]
+#[test]
+fn parse_errors() {
+    let error = |input| parse(input).unwrap_err().to_string();
 
-    print_all_tokens(&ftkit::ARGS[1]);
+    assert_eq!(error("echo |||"), "unexpected `|` at column 8");
+    assert_eq!(error("ls | | wc"), "unexpected `|` at column 6");
+    assert_eq!(error("| ls"), "unexpected `|` at column 1");
+    assert_eq!(error("a && ; b"), "unexpected `;` at column 6");
+    assert_eq!(error(";"), "unexpected `;` at column 1");
+    assert_eq!(error("a ;; b"), "unexpected `;` at column 4");
+    assert_eq!(error("cat > | wc"), "unexpected `|` at column 7");
+    assert_eq!(error("a &&"), "unexpected end of line");
+    assert_eq!(error("cat <"), "unexpected end of line");
+    assert_eq!(error("echo \"a"), "unterminated `\"` at column 6");
+    assert_eq!(error("café ; ; thé"), "unexpected `;` at column 8");
+    assert_eq!(error("ü > 😀 >"), "unexpected end of line");
+    assert_eq!(error("ü > | 😀"), "unexpected `|` at column 5");
 }
@@ -0,0 +1,164 @@
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+/// The result of a scripted session.
+struct Session {
+    stdout: String,
+    stderr: String,
+    status: i32,
+    dir: PathBuf,
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// Feeds `script` to the shell, from a fresh directory named after `name`.
+fn run(name: &str, script: &str) -> Session {
+    let dir = std::env::temp_dir().join(format!("m02ex06-{}-{name}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_m02ex06"))
+        .current_dir(&dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(script.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+
+    Session {
+        stdout: String::from_utf8(output.stdout).unwrap(),
+        stderr: String::from_utf8(output.stderr).unwrap(),
+        status: output.status.code().unwrap(),
+        dir,
+    }
+}
+
+#[test]
+fn pipelines() {
+    let session = run(
+        "pipelines",
+        "printf 'pear\\napple\\nfig\\n' | sort | head -n 2\n\
+         seq 1 100 | grep 7 | wc -l\n",
+    );
+    assert_eq!(session.stdout, "apple\nfig\n19\n");
+    assert_eq!(session.stderr, "");
+    assert_eq!(session.status, 0);
+}
+
+#[test]
+fn redirections() {
+    let session = run(
+        "redirections",
+        "echo one > out.txt\n\
+         echo two >> out.txt\n\
+         echo three > other.txt; cat other.txt >> out.txt\n\
+         cat < out.txt\n\
+         wc -l < out.txt > count.txt\n\
+         ls missing-file 2> err.txt\n\
+         ls other-file 2>> err.txt\n\
+         tr a-z A-Z < out.txt | sort > sorted.txt\n",
+    );
+    assert_eq!(session.stdout, "one\ntwo\nthree\n");
+    assert_eq!(session.stderr, "");
+
+    let read = |name| std::fs::read_to_string(session.dir.join(name)).unwrap();
+    assert_eq!(read("out.txt"), "one\ntwo\nthree\n");
+    assert_eq!(read("count.txt").trim(), "3");
+    assert!(read("err.txt").contains("missing-file"));
+    assert!(read("err.txt").contains("other-file"));
+    assert_eq!(read("sorted.txt"), "ONE\nTHREE\nTWO\n");
+
+    let session = run("missing-input", "cat < nowhere.txt\necho $?\n");
+    assert_eq!(session.stdout, "1\n");
+    assert!(session.stderr.contains("nowhere.txt"));
+}
+
+#[test]
+fn exit_statuses() {
+    let session = run(
+        "exit-statuses",
+        "true; echo $?\n\
+         false; echo $?\n\
+         sh -c 'exit 42'; echo $?\n\
+         true && echo and\n\
+         false && echo never\n\
+         false || echo or\n\
+         true || echo never\n\
+         false && echo never || echo fallback\n\
+         false | true; echo $?\n\
+         true | false; echo $?\n\
+         no-such-command; echo $?\n\
+         echo \"status: ${?}\"\n",
+    );
+    assert_eq!(
+        session.stdout,
+        "0\n1\n42\nand\nor\nfallback\n0\n1\n127\nstatus: 0\n"
+    );
+    assert_eq!(
+        session.stderr,
+        "m02ex06: no-such-command: command not found\n"
+    );
+    assert_eq!(session.status, 0);
+
+    let session = run("last-status", "false\n");
+    assert_eq!(session.status, 1);
+}
+
+#[test]
+fn builtins() {
+    let session = run(
+        "builtins",
+        "mkdir sub\n\
+         cd sub; pwd\n\
+         cd ..; pwd\n\
+         cd does-not-exist; echo $?\n\
+         export GREETING=hello NAME='big world'\n\
+         echo $GREETING ${NAME}\n\
+         sh -c 'echo $GREETING'\n\
+         export 1nvalid=x; echo $?\n",
+    );
+    let dir = session.dir.canonicalize().unwrap();
+    assert_eq!(
+        session.stdout,
+        format!(
+            "{}\n{}\n1\nhello big world\nhello\n1\n",
+            dir.join("sub").display(),
+            dir.display()
+        )
+    );
+    assert!(session.stderr.contains("cd: does-not-exist"));
+    assert!(session.stderr.contains("not a valid identifier"));
+}
+
+#[test]
+fn exit_builtin() {
+    let session = run("exit", "echo before\nexit 3\necho after\n");
+    assert_eq!(session.stdout, "before\n");
+    assert_eq!(session.status, 3);
+
+    let session = run("exit-last", "false; exit\n");
+    assert_eq!(session.status, 1);
+
+    let session = run("exit-and", "true && exit 4 || echo no; echo no\n");
+    assert_eq!(session.stdout, "");
+    assert_eq!(session.status, 4);
+}
+
+#[test]
+fn syntax_errors() {
+    let session = run("syntax", "echo ok | | wc\necho $?\necho \"two\nlines\"\n");
+    assert_eq!(session.stdout, "2\ntwo\nlines\n");
+    assert_eq!(session.stderr, "m02ex06: unexpected `|` at column 11\n");
+}
@@ -0,0 +1,147 @@
+//! Noticing when a simulation has nothing new to show.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::rule::Rule;
+
+/// How a simulation settled.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Outcome {
+    /// Every cell is dead since this generation.
+    Extinct { generation: u64 },
+    /// Generation `start + period` is the same as generation `start`, and so on forever.
+    Cycle { start: u64, period: u64 },
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::Extinct { generation } => write!(f, "every cell died at generation {generation}"),
+            Self::Cycle { start, period: 1 } => {
+                write!(f, "the board has been still since generation {start}")
+            }
+            Self::Cycle { start, period } => write!(
+                f,
+                "the board has been repeating every {period} generations since generation {start}"
+            ),
+        }
+    }
+}
+
+/// The number of generations a [`CycleDetector`] remembers, which takes a few tens of
+/// megabytes.
+const MAX_SEEN: usize = 1 << 20;
+
+/// Remembers a key for every generation seen so far, to notice when one comes back.
+///
+/// Keys are expected to be equal exactly when generations are. When they are 64-bit hashes,
+/// detection is probabilistic: boards are not compared, so a collision reports a cycle that
+/// does not exist. With at most [`MAX_SEEN`] keys remembered, this happens with a probability
+/// below one in ten million.
+///
+/// Once [`MAX_SEEN`] generations are remembered, they are all forgotten. Cycles longer than
+/// that are never noticed, and a cycle that started before is reported as starting at the first
+/// generation remembered since.
+pub struct CycleDetector {
+    /// The first generation each key was seen at.
+    seen: HashMap<u64, u64>,
+    /// Whether an empty board stays empty, which is not the case under `B0` rules.
+    empty_is_final: bool,
+}
+
+impl CycleDetector {
+    pub fn new(rule: Rule) -> Self {
+        Self {
+            seen: HashMap::new(),
+            empty_is_final: !rule.births_from_nothing(),
+        }
+    }
+
+    /// Records `generation`, and returns how the simulation settled if it did.
+    ///
+    /// When generations are not observed one by one, the start of a cycle is the first
+    /// observed generation that is part of it.
+    pub fn observe(&mut self, generation: u64, key: u64, is_empty: bool) -> Option<Outcome> {
+        if is_empty && self.empty_is_final {
+            return Some(Outcome::Extinct { generation });
+        }
+
+        if let Some(&start) = self.seen.get(&key) {
+            return Some(Outcome::Cycle {
+                start,
+                period: generation - start,
+            });
+        }
+        if self.seen.len() >= MAX_SEEN {
+            self.seen.clear();
+        }
+        self.seen.insert(key, generation);
+        None
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn observe() {
+    let mut detector = CycleDetector::new(Rule::CONWAY);
+    assert_eq!(detector.observe(0, 10, false), None);
+    assert_eq!(detector.observe(1, 20, false), None);
+    assert_eq!(detector.observe(2, 30, false), None);
+    assert_eq!(
+        detector.observe(3, 20, false),
+        Some(Outcome::Cycle {
+            start: 1,
+            period: 2
+        })
+    );
+    assert_eq!(
+        detector.observe(4, 40, true),
+        Some(Outcome::Extinct { generation: 4 })
+    );
+
+    // Under B0/S8, an empty board fills up at the next generation.
+    let mut detector = CycleDetector::new("B0/S8".parse().unwrap());
+    assert_eq!(detector.observe(0, 10, true), None);
+    assert_eq!(detector.observe(1, 20, false), None);
+    assert_eq!(
+        detector.observe(2, 10, true),
+        Some(Outcome::Cycle {
+            start: 0,
+            period: 2
+        })
+    );
+
+    // Generations are forgotten after `MAX_SEEN` of them.
+    let mut detector = CycleDetector::new(Rule::CONWAY);
+    let last = MAX_SEEN as u64;
+    for generation in 0..last {
+        assert_eq!(detector.observe(generation, generation, false), None);
+    }
+    assert_eq!(detector.observe(last, last, false), None);
+    assert_eq!(detector.observe(last + 1, 0, false), None);
+    assert_eq!(
+        detector.observe(last + 2, last, false),
+        Some(Outcome::Cycle {
+            start: last,
+            period: 2
+        })
+    );
+
+    assert_eq!(
+        Outcome::Cycle {
+            start: 5,
+            period: 1
+        }
+        .to_string(),
+        "the board has been still since generation 5"
+    );
+    assert_eq!(
+        Outcome::Cycle {
+            start: 0,
+            period: 15
+        }
+        .to_string(),
+        "the board has been repeating every 15 generations since generation 0"
+    );
+}
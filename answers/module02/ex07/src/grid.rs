@@ -0,0 +1,243 @@
+//! Cells packed one per bit, stepped a whole word of cells at a time.
+
+use std::borrow::Cow;
+use std::thread;
+
+use crate::rule::Rule;
+use crate::{Cell, Topology};
+
+const BITS: usize = u64::BITS as usize;
+
+/// A grid of cells, row after row. Cell `x` of a row is bit `x % 64` of its word `x / 64`.
+///
+/// Each row starts on a new word, and the bits past its end are always zero, so that grids
+/// with the same cells compare and hash the same.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Grid {
+    width: usize,
+    height: usize,
+    /// How many words each row takes.
+    stride: usize,
+    words: Vec<u64>,
+}
+
+/// Adds `bits` to the counters of each cell, stored as the four bits of a number.
+fn add(count: &mut [u64; 4], bits: u64) {
+    let mut carry = bits;
+    for digit in count {
+        let sum = *digit ^ carry;
+        carry &= *digit;
+        *digit = sum;
+    }
+}
+
+/// Selects the cells whose counter is `n`.
+fn equals(count: &[u64; 4], n: usize) -> u64 {
+    count.iter().enumerate().fold(!0, |acc, (i, &digit)| {
+        acc & if n >> i & 1 == 1 { digit } else { !digit }
+    })
+}
+
+impl Grid {
+    pub fn new(width: usize, height: usize) -> Self {
+        let stride = width.div_ceil(BITS);
+        Self {
+            width,
+            height,
+            stride,
+            words: vec![0; stride * height],
+        }
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Cell {
+        if self.row(y)[x / BITS] >> (x % BITS) & 1 == 1 {
+            Cell::Alive
+        } else {
+            Cell::Dead
+        }
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, cell: Cell) {
+        let word = &mut self.words[y * self.stride + x / BITS];
+        let bit = 1 << (x % BITS);
+        match cell {
+            Cell::Alive => *word |= bit,
+            Cell::Dead => *word &= !bit,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.words.fill(0);
+    }
+
+    pub fn population(&self) -> usize {
+        self.words
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+
+    /// Returns the cells row after row.
+    pub fn to_cells(&self) -> Vec<Cell> {
+        let mut cells = Vec::with_capacity(self.width * self.height);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                cells.push(self.get(x, y));
+            }
+        }
+        cells
+    }
+
+    fn row(&self, y: usize) -> &[u64] {
+        &self.words[y * self.stride..][..self.stride]
+    }
+
+    /// Returns row `y` mirrored, so that its first cell comes last.
+    fn mirrored_row(&self, y: usize) -> Vec<u64> {
+        let mut row = vec![0; self.stride];
+        for x in 0..self.width {
+            if self.get(x, y).is_alive() {
+                let x = self.width - 1 - x;
+                row[x / BITS] |= 1 << (x % BITS);
+            }
+        }
+        row
+    }
+
+    /// Returns, for each cell of word `i` of `row`, the cells west and east of it.
+    fn west_and_east(&self, row: &[u64], i: usize, wrap: bool) -> (u64, u64) {
+        let last = self.width - 1;
+        let mut west = row[i] << 1;
+        if i > 0 {
+            west |= row[i - 1] >> (BITS - 1);
+        } else if wrap {
+            west |= row[last / BITS] >> (last % BITS) & 1;
+        }
+        let mut east = row[i] >> 1;
+        if i + 1 < self.stride {
+            east |= row[i + 1] << (BITS - 1);
+        } else if wrap {
+            east |= (row[0] & 1) << (last % BITS);
+        }
+        (west, east)
+    }
+
+    /// Computes the next generation of `row` into `next`, given the rows above and below it.
+    ///
+    /// Bit `n` of `birth` and `survival` are those of the rule.
+    #[allow(clippy::too_many_arguments)]
+    fn step_row(
+        &self,
+        above: &[u64],
+        row: &[u64],
+        below: &[u64],
+        birth: u16,
+        survival: u16,
+        wrap: bool,
+        next: &mut [u64],
+    ) {
+        // The bits past the end of the row, which must stay dead.
+        let padding = (BITS - self.width % BITS) % BITS;
+        for (i, next) in next.iter_mut().enumerate() {
+            let mut count = [0; 4];
+            for (j, row) in [above, row, below].into_iter().enumerate() {
+                let (west, east) = self.west_and_east(row, i, wrap);
+                add(&mut count, west);
+                add(&mut count, east);
+                if j != 1 {
+                    add(&mut count, row[i]);
+                }
+            }
+
+            let alive = row[i];
+            let mut cells = 0;
+            for n in 0..=8 {
+                let matching = equals(&count, n);
+                if birth >> n & 1 == 1 {
+                    cells |= !alive & matching;
+                }
+                if survival >> n & 1 == 1 {
+                    cells |= alive & matching;
+                }
+            }
+            if i + 1 == self.stride {
+                cells &= !0 >> padding;
+            }
+            *next = cells;
+        }
+    }
+
+    /// Writes the next generation into `next`, which must be the same size, splitting the
+    /// rows between up to `threads` threads.
+    pub fn step_into(&self, next: &mut Grid, rule: Rule, topology: Topology, threads: usize) {
+        debug_assert_eq!((self.width, self.height), (next.width, next.height));
+        if self.width == 0 || self.height == 0 {
+            return;
+        }
+
+        let mask = |cell| {
+            (0..=8).fold(0, |mask, n| {
+                mask | (rule.next(cell, n).is_alive() as u16) << n
+            })
+        };
+        let (birth, survival) = (mask(Cell::Dead), mask(Cell::Alive));
+
+        // The rows past the top and bottom edges.
+        let last = self.height - 1;
+        let (top, bottom): (Cow<[u64]>, Cow<[u64]>) = match topology {
+            Topology::Bounded => (vec![0; self.stride].into(), vec![0; self.stride].into()),
+            Topology::Torus => (self.row(last).into(), self.row(0).into()),
+            Topology::Klein => (self.mirrored_row(last).into(), self.mirrored_row(0).into()),
+        };
+        let wrap = topology != Topology::Bounded;
+
+        let rows_per_thread = self.height.div_ceil(threads.max(1));
+        let step_rows = |first: usize, next: &mut [u64]| {
+            for (y, next) in (first..).zip(next.chunks_mut(self.stride)) {
+                let above = if y == 0 { &top } else { self.row(y - 1) };
+                let below = if y == last { &bottom } else { self.row(y + 1) };
+                self.step_row(above, self.row(y), below, birth, survival, wrap, next);
+            }
+        };
+
+        if rows_per_thread >= self.height {
+            step_rows(0, &mut next.words);
+            return;
+        }
+        let step_rows = &step_rows;
+        thread::scope(|scope| {
+            for (chunk, next) in next
+                .words
+                .chunks_mut(rows_per_thread * self.stride)
+                .enumerate()
+            {
+                scope.spawn(move || step_rows(chunk * rows_per_thread, next));
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn get_and_set() {
+    let mut grid = Grid::new(70, 3);
+    grid.set(0, 0, Cell::Alive);
+    grid.set(64, 1, Cell::Alive);
+    grid.set(69, 2, Cell::Alive);
+    assert_eq!(grid.population(), 3);
+    assert_eq!(grid.get(64, 1), Cell::Alive);
+    assert_eq!(grid.get(63, 1), Cell::Dead);
+    assert_eq!(grid.mirrored_row(2)[0], 1);
+
+    grid.set(64, 1, Cell::Dead);
+    assert_eq!(grid.get(64, 1), Cell::Dead);
+    assert_eq!(
+        grid.to_cells()
+            .iter()
+            .filter(|cell| cell.is_alive())
+            .count(),
+        2
+    );
+    grid.clear();
+    assert_eq!(grid, Grid::new(70, 3));
+}
@@ -0,0 +1,532 @@
+//! An unbounded universe, stored as a quadtree of shared nodes and advanced with Gosper's
+//! HashLife algorithm.
+//!
+//! Identical parts of the universe are stored once, and the future of each node is memoized,
+//! so regular patterns can be advanced by billions of generations in a few steps.
+
+use std::collections::HashMap;
+
+use crate::pattern::Pattern;
+use crate::rule::Rule;
+use crate::Cell;
+
+/// The index of a node in [`Universe::nodes`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct NodeId(u32);
+
+impl NodeId {
+    /// The two nodes of level 0, which are single cells.
+    const DEAD: Self = Self(0);
+    const ALIVE: Self = Self(1);
+}
+
+/// A square of `2^level` cells per side, made of four squares of the level below.
+#[derive(Debug, Clone, Copy)]
+struct Node {
+    level: u8,
+    /// The north-west, north-east, south-west and south-east quarters. Unused for cells.
+    children: [NodeId; 4],
+    population: u128,
+}
+
+/// The last generation a universe can reach. Past it, patterns could grow out of the `i64`
+/// coordinates.
+pub const MAX_GENERATION: u64 = 1 << 58;
+
+/// The number of results [`Universe::successor`] remembers before forgetting them all.
+const MEMO_LIMIT: usize = 1 << 22;
+
+/// Nodes are never removed, so that [`Universe::key`] stays the same for the same cells: the
+/// memory used grows with the number of different squares the universe ever had.
+pub struct Universe {
+    nodes: Vec<Node>,
+    /// Finds the existing node with the given children, so that none is stored twice.
+    index: HashMap<[NodeId; 4], NodeId>,
+    /// The result of [`Universe::successor`] for each node and step already computed, up to
+    /// [`MEMO_LIMIT`] of them.
+    memo: HashMap<(NodeId, u8), NodeId>,
+    /// The empty node of each level.
+    empty: Vec<NodeId>,
+    /// The whole universe, centered on `(0, 0)`. Everything outside of it is dead.
+    root: NodeId,
+    rule: Rule,
+    generation: u64,
+}
+
+impl Universe {
+    /// # Panics
+    ///
+    /// If `rule` is a `B0` rule: the universe relies on empty space staying empty.
+    pub fn new(rule: Rule) -> Self {
+        assert!(
+            !rule.births_from_nothing(),
+            "HashLife does not support B0 rules"
+        );
+        let leaf = |population| Node {
+            level: 0,
+            children: [NodeId::DEAD; 4],
+            population,
+        };
+        let mut universe = Self {
+            nodes: vec![leaf(0), leaf(1)],
+            index: HashMap::new(),
+            memo: HashMap::new(),
+            empty: vec![NodeId::DEAD],
+            root: NodeId::DEAD,
+            rule,
+            generation: 0,
+        };
+        universe.root = universe.empty(3);
+        universe
+    }
+
+    /// Copies the cells of a pattern, so that its center lands on `(0, 0)`.
+    pub fn from_pattern(pattern: &Pattern, rule: Rule) -> Self {
+        let mut universe = Self::new(rule);
+        let (left, top) = Self::centered_origin(pattern.width, pattern.height);
+        for y in 0..pattern.height {
+            for x in 0..pattern.width {
+                if pattern.get(x, y).is_alive() {
+                    universe.set(left + x as i64, top + y as i64, Cell::Alive);
+                }
+            }
+        }
+        universe
+    }
+
+    /// The coordinates of the top-left corner of a rectangle centered on `(0, 0)`.
+    pub fn centered_origin(width: usize, height: usize) -> (i64, i64) {
+        (-(width as i64 / 2), -(height as i64 / 2))
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    pub fn rule(&self) -> Rule {
+        self.rule
+    }
+
+    pub fn population(&self) -> u128 {
+        self.node(self.root).population
+    }
+
+    fn node(&self, id: NodeId) -> Node {
+        self.nodes[id.0 as usize]
+    }
+
+    fn level(&self) -> u8 {
+        self.node(self.root).level
+    }
+
+    /// The node whose quarters are `nw`, `ne`, `sw` and `se`.
+    fn join(&mut self, nw: NodeId, ne: NodeId, sw: NodeId, se: NodeId) -> NodeId {
+        let children = [nw, ne, sw, se];
+        if let Some(&id) = self.index.get(&children) {
+            return id;
+        }
+
+        let node = Node {
+            level: self.node(nw).level + 1,
+            children,
+            population: children.iter().map(|&c| self.node(c).population).sum(),
+        };
+        let id = NodeId(self.nodes.len() as u32);
+        self.nodes.push(node);
+        self.index.insert(children, id);
+        id
+    }
+
+    fn empty(&mut self, level: u8) -> NodeId {
+        while self.empty.len() <= level as usize {
+            let e = *self.empty.last().unwrap();
+            let bigger = self.join(e, e, e, e);
+            self.empty.push(bigger);
+        }
+        self.empty[level as usize]
+    }
+
+    /// Doubles the size of the universe, keeping it centered on `(0, 0)`.
+    fn expand(&mut self) {
+        let level = self.level();
+        let [nw, ne, sw, se] = self.node(self.root).children;
+        let e = self.empty(level - 1);
+        let nw = self.join(e, e, e, nw);
+        let ne = self.join(e, e, ne, e);
+        let sw = self.join(e, sw, e, e);
+        let se = self.join(se, e, e, e);
+        self.root = self.join(nw, ne, sw, se);
+    }
+
+    /// Half the size of the root node, which spans `-half..half` on both axes.
+    fn half(&self) -> i64 {
+        1 << (self.level() - 1)
+    }
+
+    pub fn get(&self, x: i64, y: i64) -> Cell {
+        let half = self.half();
+        if !(-half..half).contains(&x) || !(-half..half).contains(&y) {
+            return Cell::Dead;
+        }
+
+        let (mut id, mut x, mut y) = (self.root, x + half, y + half);
+        loop {
+            let node = self.node(id);
+            if node.level == 0 {
+                break if id == NodeId::ALIVE {
+                    Cell::Alive
+                } else {
+                    Cell::Dead
+                };
+            }
+            let half = 1 << (node.level - 1);
+            let quarter = (x >= half) as usize + 2 * (y >= half) as usize;
+            id = node.children[quarter];
+            x %= half;
+            y %= half;
+        }
+    }
+
+    pub fn set(&mut self, x: i64, y: i64, cell: Cell) {
+        while !(-self.half()..self.half()).contains(&x) || !(-self.half()..self.half()).contains(&y)
+        {
+            self.expand();
+        }
+        let half = self.half();
+        self.root = self.set_in(self.root, x + half, y + half, cell);
+    }
+
+    /// Returns `id` with the cell at `(x, y)`, relative to its top-left corner, replaced.
+    fn set_in(&mut self, id: NodeId, x: i64, y: i64, cell: Cell) -> NodeId {
+        let node = self.node(id);
+        if node.level == 0 {
+            return match cell {
+                Cell::Alive => NodeId::ALIVE,
+                Cell::Dead => NodeId::DEAD,
+            };
+        }
+
+        let half = 1 << (node.level - 1);
+        let quarter = (x >= half) as usize + 2 * (y >= half) as usize;
+        let mut children = node.children;
+        children[quarter] = self.set_in(children[quarter], x % half, y % half, cell);
+        let [nw, ne, sw, se] = children;
+        self.join(nw, ne, sw, se)
+    }
+
+    /// Kills every cell, keeping the generation count.
+    pub fn clear(&mut self) {
+        self.root = self.empty(3);
+    }
+
+    /// Identifies the current generation: two generations have the same key exactly when they
+    /// have the same cells at the same place.
+    pub fn key(&mut self) -> u64 {
+        // Nodes are never removed, so the smallest node that contains the whole pattern always
+        // has the same id for the same cells.
+        let mut id = self.root;
+        while self.node(id).level > 3 {
+            let center = self.center(id);
+            if self.node(center).population != self.node(id).population {
+                break;
+            }
+            id = center;
+        }
+        id.0 as u64
+    }
+
+    /// Advances the universe by `generations` generations.
+    ///
+    /// # Panics
+    ///
+    /// If that goes past [`MAX_GENERATION`].
+    pub fn step(&mut self, generations: u64) {
+        assert!(
+            self.generation
+                .checked_add(generations)
+                .is_some_and(|end| end <= MAX_GENERATION),
+            "HashLife cannot go past generation {MAX_GENERATION}"
+        );
+        for j in 0..64 {
+            if generations & 1 << j == 0 {
+                continue;
+            }
+
+            // The pattern must stay within the part of the root that `successor` computes. It
+            // grows by at most one cell per generation, so it has to start in the central
+            // sixteenth of a root at least four times as large as the step.
+            while self.level() < j + 3 || self.inner_population() != self.population() {
+                self.expand();
+            }
+            if self.memo.len() > MEMO_LIMIT {
+                self.memo.clear();
+            }
+            self.root = self.successor(self.root, j);
+            self.generation += 1 << j;
+        }
+    }
+
+    /// The population of the central square of the root, whose sides are a quarter of the
+    /// root's.
+    fn inner_population(&self) -> u128 {
+        let [nw, ne, sw, se] = self.node(self.root).children;
+        let grandchild = |id: NodeId, quarter: usize| {
+            let child = self.node(id).children[quarter];
+            self.node(self.node(child).children[quarter]).population
+        };
+        grandchild(nw, 3) + grandchild(ne, 2) + grandchild(sw, 1) + grandchild(se, 0)
+    }
+
+    /// Returns the center of a node of level `k >= 2`, half its size, `2^j` generations later,
+    /// with `j <= k - 2`.
+    fn successor(&mut self, id: NodeId, j: u8) -> NodeId {
+        if let Some(&result) = self.memo.get(&(id, j)) {
+            return result;
+        }
+
+        let node = self.node(id);
+        let result = if node.population == 0 {
+            self.empty(node.level - 1)
+        } else if node.level == 2 {
+            self.step_4x4(id)
+        } else {
+            let [nw, ne, sw, se] = node.children.map(|c| self.node(c).children);
+
+            // Nine overlapping squares half the size of the node.
+            let n = [
+                node.children[0],
+                self.join(nw[1], ne[0], nw[3], ne[2]),
+                node.children[1],
+                self.join(nw[2], nw[3], sw[0], sw[1]),
+                self.join(nw[3], ne[2], sw[1], se[0]),
+                self.join(ne[2], ne[3], se[0], se[1]),
+                node.children[2],
+                self.join(sw[1], se[0], sw[3], se[2]),
+                node.children[3],
+            ];
+
+            // At full speed, both halves of the time are spent advancing. Otherwise, the first
+            // half only shrinks the squares.
+            let full_speed = j == node.level - 2;
+            let c = n.map(|n| {
+                if full_speed {
+                    self.successor(n, j - 1)
+                } else {
+                    self.center(n)
+                }
+            });
+            let second_step = if full_speed { j - 1 } else { j };
+
+            let quarters = [
+                self.join(c[0], c[1], c[3], c[4]),
+                self.join(c[1], c[2], c[4], c[5]),
+                self.join(c[3], c[4], c[6], c[7]),
+                self.join(c[4], c[5], c[7], c[8]),
+            ];
+            let [nw, ne, sw, se] = quarters.map(|q| self.successor(q, second_step));
+            self.join(nw, ne, sw, se)
+        };
+
+        self.memo.insert((id, j), result);
+        result
+    }
+
+    /// The center of a node, half its size.
+    fn center(&mut self, id: NodeId) -> NodeId {
+        let [nw, ne, sw, se] = self.node(id).children.map(|c| self.node(c).children);
+        self.join(nw[3], ne[2], sw[1], se[0])
+    }
+
+    /// Computes the next generation of the center of a node of level 2, directly.
+    fn step_4x4(&mut self, id: NodeId) -> NodeId {
+        let mut grid = [[false; 4]; 4];
+        for (quarter, &child) in self.node(id).children.iter().enumerate() {
+            for (cell, &leaf) in self.node(child).children.iter().enumerate() {
+                let x = (quarter % 2) * 2 + cell % 2;
+                let y = (quarter / 2) * 2 + cell / 2;
+                grid[y][x] = leaf == NodeId::ALIVE;
+            }
+        }
+
+        let mut next = [NodeId::DEAD; 4];
+        for (i, result) in next.iter_mut().enumerate() {
+            let (x, y) = (1 + i % 2, 1 + i / 2);
+            let block = grid[y - 1..=y + 1]
+                .iter()
+                .flat_map(|row| &row[x - 1..=x + 1]);
+            let neighbors = block.filter(|&&alive| alive).count() as u32 - grid[y][x] as u32;
+            let cell = if grid[y][x] { Cell::Alive } else { Cell::Dead };
+            *result = match self.rule.next(cell, neighbors) {
+                Cell::Alive => NodeId::ALIVE,
+                Cell::Dead => NodeId::DEAD,
+            };
+        }
+
+        let [nw, ne, sw, se] = next;
+        self.join(nw, ne, sw, se)
+    }
+
+    /// Renders a `width` by `height` rectangle whose top-left corner is at `(left, top)`.
+    ///
+    /// Each cell of the result stands for a square of `2^zoom` cells per side of the universe,
+    /// and is alive when any of them is.
+    pub fn viewport(&self, left: i64, top: i64, width: usize, height: usize, zoom: u8) -> Pattern {
+        let mut pattern = Pattern::empty(width, height);
+        let size = 1i64 << zoom;
+        let half = self.half();
+        for y in 0..height {
+            for x in 0..width {
+                let block_x = left + x as i64 * size;
+                let block_y = top + y as i64 * size;
+                if self.any_alive(self.root, -half, -half, block_x, block_y, size) {
+                    pattern.cells[x + y * width] = Cell::Alive;
+                }
+            }
+        }
+        pattern.rule = Some(self.rule);
+        pattern
+    }
+
+    /// Whether node `id`, whose top-left corner is at `(x, y)`, has a live cell in the square of
+    /// side `size` at `(block_x, block_y)`.
+    fn any_alive(&self, id: NodeId, x: i64, y: i64, block_x: i64, block_y: i64, size: i64) -> bool {
+        let node = self.node(id);
+        let side = 1i64 << node.level;
+        let overlaps =
+            x < block_x + size && block_x < x + side && y < block_y + size && block_y < y + side;
+        if node.population == 0 || !overlaps {
+            return false;
+        }
+        let inside = block_x <= x
+            && x + side <= block_x + size
+            && block_y <= y
+            && y + side <= block_y + size;
+        if inside || node.level == 0 {
+            return true;
+        }
+
+        let half = side / 2;
+        node.children.iter().enumerate().any(|(quarter, &child)| {
+            let child_x = x + (quarter % 2) as i64 * half;
+            let child_y = y + (quarter / 2) as i64 * half;
+            self.any_alive(child, child_x, child_y, block_x, block_y, size)
+        })
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn set_and_get() {
+    let mut universe = Universe::new(Rule::CONWAY);
+    let cells = [(0, 0), (-1, 5), (1000, -2000), (-123456, 789)];
+    for (x, y) in cells {
+        universe.set(x, y, Cell::Alive);
+    }
+    assert_eq!(universe.population(), 4);
+    for (x, y) in cells {
+        assert_eq!(universe.get(x, y), Cell::Alive);
+        assert_eq!(universe.get(x + 1, y), Cell::Dead);
+    }
+    universe.set(1000, -2000, Cell::Dead);
+    assert_eq!(universe.population(), 3);
+    assert_eq!(universe.get(1000, -2000), Cell::Dead);
+    assert_eq!(universe.get(i64::MAX / 2, 0), Cell::Dead);
+}
+
+#[cfg(test)]
+#[test]
+fn viewport() {
+    let glider = Pattern::parse_rle("x = 3, y = 3\nbob$2bo$3o!").unwrap();
+    let mut universe = Universe::from_pattern(&glider, Rule::CONWAY);
+    assert_eq!(universe.viewport(-1, -1, 3, 3, 0).cells, glider.cells);
+
+    universe.step(4 * 100);
+    assert_eq!(universe.generation(), 400);
+    assert_eq!(universe.population(), 5);
+    assert_eq!(universe.viewport(99, 99, 3, 3, 0).cells, glider.cells);
+    assert_eq!(universe.viewport(-1, -1, 3, 3, 0).population(), 0);
+
+    // Zoomed out, the glider fits in a single cell of 8 by 8 cells.
+    let zoomed = universe.viewport(88, 88, 2, 2, 3);
+    assert_eq!(zoomed.to_cells(), "\n.O\n");
+}
+
+#[cfg(test)]
+#[test]
+fn same_as_board() {
+    for rule in ["B3/S23", "B36/S23", "B3678/S34678"] {
+        // A small soup in the middle of a large board, which it cannot reach the edges of in
+        // the number of generations simulated.
+        let mut board = crate::Board::empty(200, 200);
+        board.topology = crate::Topology::Bounded;
+        board.rule = rule.parse().unwrap();
+        let soup = crate::Board::new(16, 16, 40, &mut crate::Random::new(7));
+        board.place(&soup.to_pattern(), 92, 92);
+        let mut universe = Universe::from_pattern(&board.to_pattern(), board.rule);
+        let (left, top) = Universe::centered_origin(200, 200);
+
+        let mut generation = 0;
+        for step in [1, 2, 7, 13, 41] {
+            for _ in 0..step {
+                board.step();
+            }
+            universe.step(step);
+            generation += step;
+            assert_eq!(universe.generation(), generation);
+            let viewport = universe.viewport(left, top, 200, 200, 0);
+            assert_eq!(
+                viewport.cells,
+                board.to_pattern().cells,
+                "{rule}, generation {generation}"
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn glider_gun_for_a_billion_generations() {
+    // A gun grows linearly rather than quadratically like a breeder, but is much smaller to
+    // write, and still has to be advanced by about 2^30 generations at once.
+    let gun = Pattern::parse_rle(
+        "x = 36, y = 9\n24bo$22bobo$12b2o6b2o12b2o$11bo3bo4b2o12b2o$2o8bo5bo3b2o$2o8bo3b\
+         ob2o4bobo$10bo5bo7bo$11bo3bo$12b2o!",
+    )
+    .unwrap();
+    let mut universe = Universe::from_pattern(&gun, Rule::CONWAY);
+
+    // Every 30 generations, the gun is back to its initial state and has emitted a glider of 5
+    // cells.
+    let periods = 1 << 25;
+    universe.step(30 * periods);
+    assert_eq!(universe.population(), 36 + 5 * periods as u128);
+    let (left, top) = Universe::centered_origin(gun.width, gun.height);
+    assert_eq!(
+        universe.viewport(left, top, gun.width, gun.height, 0).cells,
+        gun.cells
+    );
+}
+
+#[cfg(test)]
+#[test]
+fn keys() {
+    let glider = Pattern::parse_rle("x = 3, y = 3\nbob$2bo$3o!").unwrap();
+    let mut universe = Universe::from_pattern(&glider, Rule::CONWAY);
+    let start = universe.key();
+    universe.step(4);
+    assert_ne!(universe.key(), start);
+
+    // The same cells at the same place, in a root of another size.
+    let mut other = Universe::from_pattern(&glider, Rule::CONWAY);
+    other.set(-1000, 0, Cell::Alive);
+    other.set(-1000, 0, Cell::Dead);
+    assert_eq!(other.key(), start);
+
+    let blinker = Pattern::parse_rle("x = 3, y = 1\n3o!").unwrap();
+    let mut universe = Universe::from_pattern(&blinker, Rule::CONWAY);
+    let start = universe.key();
+    universe.step(1);
+    assert_ne!(universe.key(), start);
+    universe.step(1);
+    assert_eq!(universe.key(), start);
+}
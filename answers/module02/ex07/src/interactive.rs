@@ -0,0 +1,268 @@
+//! Running the simulation with keyboard controls.
+
+use std::io::{self, Read, Write};
+use std::process::{Command, Stdio};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+use crate::cycle::{CycleDetector, Outcome};
+use crate::simulation::Simulation;
+use crate::{print_frame, Config, Random};
+
+const HELP: &str =
+    "space: pause  n: step  +/-: speed  r: randomize  c: clear  arrows/hjkl: move  t: toggle  q: quit";
+
+/// The shortest and longest delays between two generations.
+const FASTEST: Duration = Duration::from_millis(10);
+const SLOWEST: Duration = Duration::from_millis(2560);
+
+/// Keeps the terminal in raw mode, so that keys are read as soon as they are pressed and are
+/// not echoed, until dropped.
+struct RawMode {
+    /// The previous settings, as printed by `stty -g`.
+    saved: String,
+}
+
+/// Runs `stty` on the terminal of the standard input.
+fn stty(args: &[&str]) -> io::Result<String> {
+    let output = Command::new("stty")
+        .args(args)
+        .stdin(Stdio::inherit())
+        .stderr(Stdio::null())
+        .output()?;
+    if !output.status.success() {
+        return Err(io::Error::other("the standard input is not a terminal"));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+impl RawMode {
+    fn enable() -> io::Result<Self> {
+        let saved = stty(&["-g"])?;
+        // `opost` keeps turning `\n` into `\r\n` when printing.
+        stty(&["raw", "-echo", "opost"])?;
+        // Switch to the alternate screen and hide the cursor.
+        print!("\x1B[?1049h\x1B[?25l");
+        Ok(Self { saved })
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        print!("\x1B[?25h\x1B[?1049l");
+        let _ = io::stdout().flush();
+        let _ = stty(&[&self.saved]);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Key {
+    Char(char),
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+/// Decodes the keys in a chunk of input. Escape sequences are expected not to be split across
+/// chunks, which holds for keys typed by hand.
+fn decode(bytes: &[u8]) -> Vec<Key> {
+    let mut keys = Vec::new();
+    let mut rest = bytes;
+    while let Some((&byte, tail)) = rest.split_first() {
+        rest = tail;
+        let key = match (byte, rest) {
+            (0x1B, [b'[' | b'O', arrow, tail @ ..]) => {
+                rest = tail;
+                match arrow {
+                    b'A' => Key::Up,
+                    b'B' => Key::Down,
+                    b'C' => Key::Right,
+                    b'D' => Key::Left,
+                    _ => continue,
+                }
+            }
+            (b'\r', _) => Key::Char('\n'),
+            // Other non-ASCII bytes can safely be ignored.
+            _ if byte.is_ascii() => Key::Char(byte as char),
+            _ => continue,
+        };
+        keys.push(key);
+    }
+    keys
+}
+
+/// Reads the standard input on another thread, so that the simulation keeps running while no
+/// key is pressed.
+fn read_keys() -> mpsc::Receiver<Vec<u8>> {
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || {
+        let mut buf = [0; 64];
+        while let Ok(n @ 1..) = io::stdin().read(&mut buf) {
+            if sender.send(buf[..n].to_vec()).is_err() {
+                break;
+            }
+        }
+    });
+    receiver
+}
+
+/// The state of the controls, next to the simulation itself.
+struct Controls {
+    paused: bool,
+    delay: Duration,
+    cursor: (usize, usize),
+    detector: CycleDetector,
+    /// How the simulation settled, if it did since the last edit.
+    outcome: Option<Outcome>,
+}
+
+impl Controls {
+    /// Records the current generation, remembering how the simulation settled.
+    fn observe(&mut self, simulation: &mut impl Simulation) {
+        if self.outcome.is_none() {
+            let generation = simulation.generation();
+            let key = simulation.key();
+            let is_empty = simulation.population() == 0;
+            self.outcome = self.detector.observe(generation, key, is_empty);
+        }
+    }
+
+    /// Advances to the next frame, or pauses if the simulation cannot go further.
+    fn advance(&mut self, simulation: &mut impl Simulation) {
+        let generation = simulation.generation();
+        simulation.advance(u64::MAX);
+        if simulation.generation() == generation {
+            self.paused = true;
+        } else {
+            self.observe(simulation);
+        }
+    }
+
+    /// Forgets the previous generations, after the board was edited.
+    fn edited(&mut self, simulation: &mut impl Simulation) {
+        self.detector = CycleDetector::new(simulation.rule());
+        self.outcome = None;
+        self.observe(simulation);
+    }
+
+    fn status(&self, simulation: &impl Simulation) -> String {
+        let mut status = format!(
+            "generation {}  population {}  {:.1} fps  {}",
+            simulation.generation(),
+            simulation.population(),
+            1.0 / self.delay.as_secs_f32(),
+            if self.paused { "paused" } else { "running" },
+        );
+        if let Some(outcome) = self.outcome {
+            status += &format!("  ({outcome})");
+        }
+        status
+    }
+}
+
+fn draw(simulation: &impl Simulation, controls: &Controls) {
+    print!("\x1B[H");
+    print_frame(&simulation.frame(), false, Some(controls.cursor));
+    println!("\x1B[K{}", controls.status(simulation));
+    print!("\x1B[K{HELP}");
+    let _ = io::stdout().flush();
+}
+
+/// Runs the simulation until `q` is pressed.
+pub fn run(simulation: &mut impl Simulation, config: &Config) -> io::Result<()> {
+    let _raw_mode = RawMode::enable()?;
+    let keys = read_keys();
+    let mut random = Random::new(config.seed);
+    let frame = simulation.frame();
+    let (width, height) = (frame.width.max(1), frame.height.max(1));
+
+    let mut controls = Controls {
+        paused: false,
+        delay: Duration::from_millis(100),
+        cursor: (width / 2, height / 2),
+        detector: CycleDetector::new(simulation.rule()),
+        outcome: None,
+    };
+    controls.observe(simulation);
+    print!("\x1B[2J");
+
+    let mut next_frame = Instant::now() + controls.delay;
+    loop {
+        draw(simulation, &controls);
+
+        let timeout = if controls.paused {
+            Duration::from_secs(3600)
+        } else {
+            next_frame.saturating_duration_since(Instant::now())
+        };
+        let bytes = match keys.recv_timeout(timeout) {
+            Ok(bytes) => bytes,
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                if !controls.paused {
+                    controls.advance(simulation);
+                    next_frame = Instant::now() + controls.delay;
+                }
+                continue;
+            }
+            Err(mpsc::RecvTimeoutError::Disconnected) => return Ok(()),
+        };
+
+        for key in decode(&bytes) {
+            let (x, y) = controls.cursor;
+            match key {
+                // `Ctrl+C` does not send a signal in raw mode.
+                Key::Char('q' | '\x03') => return Ok(()),
+                Key::Char(' ') => controls.paused = !controls.paused,
+                Key::Char('n' | '.') => {
+                    controls.paused = true;
+                    controls.advance(simulation);
+                }
+                Key::Char('+' | '=') => controls.delay = (controls.delay / 2).max(FASTEST),
+                Key::Char('-') => controls.delay = (controls.delay * 2).min(SLOWEST),
+                Key::Char('r') => {
+                    let percentage = if config.percentage > 0 {
+                        config.percentage
+                    } else {
+                        30
+                    };
+                    simulation.randomize(percentage, &mut random);
+                    controls.edited(simulation);
+                }
+                Key::Char('c') => {
+                    simulation.clear();
+                    controls.edited(simulation);
+                }
+                Key::Char('t' | '\n') => {
+                    simulation.toggle(x, y);
+                    controls.edited(simulation);
+                }
+                Key::Up | Key::Char('k') => controls.cursor.1 = (y + height - 1) % height,
+                Key::Down | Key::Char('j') => controls.cursor.1 = (y + 1) % height,
+                Key::Left | Key::Char('h') => controls.cursor.0 = (x + width - 1) % width,
+                Key::Right | Key::Char('l') => controls.cursor.0 = (x + 1) % width,
+                Key::Char(_) => (),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn decode_keys() {
+    assert_eq!(
+        decode(b"q \x1B[A\x1B[Bn\x1BOC\x1B[D\r"),
+        [
+            Key::Char('q'),
+            Key::Char(' '),
+            Key::Up,
+            Key::Down,
+            Key::Char('n'),
+            Key::Right,
+            Key::Left,
+            Key::Char('\n'),
+        ]
+    );
+    // Unknown escape sequences and non-ASCII bytes are skipped.
+    assert_eq!(decode(b"\x1B[Zx\xC3\xA9"), [Key::Char('x')]);
+}
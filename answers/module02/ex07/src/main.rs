@@ -1,20 +1,201 @@
+mod cycle;
+mod grid;
+mod hashlife;
+mod interactive;
+mod pattern;
+mod rule;
+mod simulation;
+
+use std::collections::hash_map::DefaultHasher;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+use std::process::ExitCode;
+use std::str::FromStr;
+
+use cycle::{CycleDetector, Outcome};
+use grid::Grid;
+use hashlife::Universe;
+use pattern::{Format, Pattern, PatternError};
+use rule::Rule;
+use simulation::{Simulation, Viewport};
+
+#[derive(Debug)]
 enum ParseError {
-    InvalidWidth { arg: &'static str },
-    InvalidHeight { arg: &'static str },
-    InvalidPercentage { arg: &'static str },
+    InvalidWidth {
+        arg: &'static str,
+    },
+    InvalidHeight {
+        arg: &'static str,
+    },
+    InvalidPercentage {
+        arg: &'static str,
+    },
+    InvalidPosition {
+        arg: &'static str,
+    },
+    InvalidFormat {
+        arg: &'static str,
+    },
+    InvalidRule {
+        arg: &'static str,
+    },
+    InvalidTopology {
+        arg: &'static str,
+    },
+    InvalidStep {
+        arg: &'static str,
+    },
+    InvalidZoom {
+        arg: &'static str,
+    },
+    InvalidSeed {
+        arg: &'static str,
+    },
+    InvalidGenerations {
+        arg: &'static str,
+    },
+    TopologyWithHashLife,
+    /// `--step` or `--generations` goes past [`hashlife::MAX_GENERATION`].
+    TooFarForHashLife {
+        generations: u64,
+    },
+    /// HashLife needs empty space to stay empty, which `B0` rules do not do.
+    B0WithHashLife {
+        rule: Rule,
+    },
+    InteractiveWithHeadless,
+    UnknownOption {
+        arg: &'static str,
+    },
+    MissingValue {
+        option: &'static str,
+    },
+    UnknownPatternFormat {
+        path: &'static str,
+    },
+    CannotReadPattern {
+        path: &'static str,
+        error: std::io::Error,
+    },
+    InvalidPattern {
+        path: &'static str,
+        error: PatternError,
+    },
+    PatternTooLarge {
+        path: &'static str,
+    },
     TooManyArguments,
     NotEnoughArguments,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidWidth { arg } => write!(f, "'{arg}' is not a valid width"),
+            Self::InvalidHeight { arg } => write!(f, "'{arg}' is not a valid height"),
+            Self::InvalidPercentage { arg } => write!(f, "'{arg}' is not a valid percentage"),
+            Self::InvalidPosition { arg } => {
+                write!(f, "'{arg}' is not a valid position, expected 'X,Y'")
+            }
+            Self::InvalidFormat { arg } => {
+                write!(
+                    f,
+                    "'{arg}' is not a valid format, expected 'rle' or 'cells'"
+                )
+            }
+            Self::InvalidRule { arg } => {
+                write!(f, "'{arg}' is not a valid rule, expected 'B.../S...'")
+            }
+            Self::InvalidTopology { arg } => write!(
+                f,
+                "'{arg}' is not a valid topology, expected 'bounded', 'torus' or 'klein'"
+            ),
+            Self::InvalidStep { arg } => {
+                write!(f, "'{arg}' is not a valid number of generations")
+            }
+            Self::InvalidZoom { arg } => write!(f, "'{arg}' is not a valid zoom level"),
+            Self::InvalidSeed { arg } => write!(f, "'{arg}' is not a valid seed"),
+            Self::InvalidGenerations { arg } => {
+                write!(f, "'{arg}' is not a valid number of generations")
+            }
+            Self::InteractiveWithHeadless => {
+                f.write_str("'--interactive' cannot be used with '--headless' or '--dump'")
+            }
+            Self::TopologyWithHashLife => {
+                f.write_str("'--hashlife' runs on an unbounded plane, without '--topology'")
+            }
+            Self::TooFarForHashLife { generations } => write!(
+                f,
+                "'--hashlife' cannot reach generation {generations}, the last one is {}",
+                hashlife::MAX_GENERATION
+            ),
+            Self::B0WithHashLife { rule } => {
+                write!(
+                    f,
+                    "'--hashlife' cannot run {rule}, where cells are born from nothing"
+                )
+            }
+            Self::UnknownOption { arg } => write!(f, "unknown option '{arg}'"),
+            Self::MissingValue { option } => write!(f, "'{option}' expects a value"),
+            Self::UnknownPatternFormat { path } => {
+                write!(f, "'{path}' is neither a '.rle' nor a '.cells' file")
+            }
+            Self::CannotReadPattern { path, error } => write!(f, "cannot read '{path}': {error}"),
+            Self::InvalidPattern { path, error } => write!(f, "{path}: {error}"),
+            Self::PatternTooLarge { path } => {
+                write!(f, "'{path}' does not fit in the board at this position")
+            }
+            Self::TooManyArguments => f.write_str("too many arguments"),
+            Self::NotEnoughArguments => f.write_str("not enough arguments"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 enum Cell {
     Dead,
     Alive,
 }
 
+/// Fills boards with random cells. Given the same `--seed`, it fills them the same way, so that
+/// an interesting board can be run again.
+///
+/// This is SplitMix64, which is fast and good enough to scatter cells.
+struct Random {
+    state: u64,
+}
+
+impl Random {
+    fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// Picks a seed from the current time, for when `--seed` is not given.
+    fn seed() -> u64 {
+        std::time::SystemTime::UNIX_EPOCH
+            .elapsed()
+            .map_or(0, |elapsed| elapsed.as_nanos() as u64)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a number in `0..n`. Small numbers are very slightly more likely than others,
+    /// which does not matter for percentages.
+    fn below(&mut self, n: u32) -> u32 {
+        (self.next_u64() % n as u64) as u32
+    }
+}
+
 impl Cell {
-    pub fn random_cell(percent_alive: u32) -> Self {
-        let n = ftkit::random_number(0..100) as u32;
+    fn random_cell(percent_alive: u32, random: &mut Random) -> Self {
+        let n = random.below(100);
 
         if n < percent_alive {
             Self::Alive
@@ -33,80 +214,140 @@ impl Cell {
     }
 }
 
+/// What lies past the edges of the board.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+enum Topology {
+    /// Dead cells: patterns behave as on a finite plane.
+    Bounded,
+    /// The opposite edge: leaving through the right comes back from the left, and leaving
+    /// through the bottom comes back from the top.
+    #[default]
+    Torus,
+    /// Like a torus horizontally, but leaving through the bottom (or top) comes back from the
+    /// top (or bottom) mirrored, so that the right side becomes the left side.
+    Klein,
+}
+
+impl FromStr for Topology {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bounded" => Ok(Self::Bounded),
+            "torus" => Ok(Self::Torus),
+            "klein" => Ok(Self::Klein),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Boards are only stepped on several threads when each thread gets at least this many cells.
+const CELLS_PER_THREAD: usize = 1 << 16;
+
 struct Board {
     width: usize,
     height: usize,
-    cells: Vec<Cell>,
+    cells: Grid,
+    /// Where the next generation is computed, kept around to avoid allocating it every time.
+    next: Grid,
+    /// How many threads to step the board with.
+    threads: usize,
+    rule: Rule,
+    topology: Topology,
+    /// How many times the board has been stepped.
+    generation: u64,
 }
 
 impl Board {
-    fn new(width: usize, height: usize, percentage: u32) -> Self {
-        let mut cells = Vec::new();
-        for _ in 0..width * height {
-            cells.push(Cell::random_cell(percentage));
+    fn new(width: usize, height: usize, percentage: u32, random: &mut Random) -> Self {
+        let mut cells = Grid::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                cells.set(x, y, Cell::random_cell(percentage, random));
+            }
         }
+        let threads = std::thread::available_parallelism()
+            .map_or(1, |threads| threads.get())
+            .min(width * height / CELLS_PER_THREAD)
+            .max(1);
 
         Self {
             width,
             height,
+            next: cells.clone(),
             cells,
+            threads,
+            rule: Rule::CONWAY,
+            topology: Topology::Torus,
+            generation: 0,
         }
     }
 
-    fn from_args() -> Result<Self, ParseError> {
-        if ftkit::ARGS.len() < 4 {
-            return Err(ParseError::NotEnoughArguments);
-        }
+    #[cfg(test)]
+    fn empty(width: usize, height: usize) -> Self {
+        Self::new(width, height, 0, &mut Random::new(0))
+    }
 
-        if ftkit::ARGS.len() > 4 {
-            return Err(ParseError::TooManyArguments);
+    /// Places `pattern` with its top-left corner at `(x, y)`. The pattern must fit in the board.
+    fn place(&mut self, pattern: &Pattern, x: usize, y: usize) {
+        for py in 0..pattern.height {
+            for px in 0..pattern.width {
+                if pattern.get(px, py).is_alive() {
+                    self.cells.set(x + px, y + py, Cell::Alive);
+                }
+            }
         }
+    }
 
-        let width = match ftkit::ARGS[1].parse() {
-            Ok(ok) => ok,
-            Err(_) => {
-                return Err(ParseError::InvalidWidth {
-                    arg: &ftkit::ARGS[1],
-                })
-            }
-        };
+    /// Returns the current generation as a pattern the size of the board.
+    fn to_pattern(&self) -> Pattern {
+        Pattern {
+            width: self.width,
+            height: self.height,
+            cells: self.cells.to_cells(),
+            rule: Some(self.rule),
+        }
+    }
 
-        let height = match ftkit::ARGS[2].parse() {
-            Ok(ok) => ok,
-            Err(_) => {
-                return Err(ParseError::InvalidHeight {
-                    arg: &ftkit::ARGS[2],
-                })
+    /// Returns the cell at `(x, y)`, which may be outside of the board, depending on its
+    /// topology.
+    #[cfg(test)]
+    fn get(&self, x: isize, y: isize) -> Cell {
+        let width = self.width as isize;
+        let height = self.height as isize;
+        let (x, y) = match self.topology {
+            Topology::Bounded => {
+                if !(0..width).contains(&x) || !(0..height).contains(&y) {
+                    return Cell::Dead;
+                }
+                (x, y)
             }
-        };
-
-        let percentage = match ftkit::ARGS[3].parse::<u32>() {
-            Ok(ok) if ok <= 100 => ok,
-            _ => {
-                return Err(ParseError::InvalidPercentage {
-                    arg: &ftkit::ARGS[3],
-                })
+            Topology::Torus => (x.rem_euclid(width), y.rem_euclid(height)),
+            Topology::Klein => {
+                // Each crossing of the top or bottom edge mirrors the board.
+                let x = if y.div_euclid(height) % 2 == 0 {
+                    x
+                } else {
+                    width - 1 - x
+                };
+                (x.rem_euclid(width), y.rem_euclid(height))
             }
         };
 
-        Ok(Self::new(width, height, percentage))
+        self.cells.get(x as usize, y as usize)
     }
 
-    fn get(&self, mut x: isize, mut y: isize) -> Cell {
-        while x < 0 {
-            x += self.width as isize;
-        }
-        let x = x as usize % self.width;
-        while y < 0 {
-            y += self.height as isize;
-        }
-        let y = y as usize % self.height;
-
-        self.cells[x + y * self.width]
+    fn step(&mut self) {
+        self.cells
+            .step_into(&mut self.next, self.rule, self.topology, self.threads);
+        std::mem::swap(&mut self.cells, &mut self.next);
+        self.generation += 1;
     }
 
-    fn step(&mut self) {
-        let mut next_board = Vec::new();
+    /// Computes the next generation one cell at a time, which [`Board::step`] must agree with.
+    #[cfg(test)]
+    fn step_one_by_one(&mut self) {
+        let mut next_board = Grid::new(self.width, self.height);
 
         for y in 0..self.height as isize {
             for x in 0..self.width as isize {
@@ -119,73 +360,797 @@ impl Board {
                     + self.get(x - 1, y + 1).is_alive() as u32
                     + self.get(x - 1, y - 1).is_alive() as u32;
 
-                let new_cell = match (self.get(x, y), neighbors) {
-                    (Cell::Alive, 2 | 3) => Cell::Alive,
-                    (Cell::Dead, 3) => Cell::Alive,
-                    _ => Cell::Dead,
-                };
-
-                next_board.push(new_cell);
+                let cell = self.rule.next(self.get(x, y), neighbors);
+                next_board.set(x as usize, y as usize, cell);
             }
         }
 
         self.cells = next_board;
+        self.generation += 1;
     }
 
-    #[allow(clippy::print_with_newline)]
-    fn print(&self, clear: bool) {
-        if clear {
-            print!("\x1B[{}A\x1B[J", self.height + 2);
-        }
-        for _ in 0..self.width {
-            print!("░░");
-        }
-        print!("░░░░\n");
-        for y in 0..self.height {
-            print!("░░");
-            for x in 0..self.width {
-                let r = ((x as f32 / self.width as f32) * 255.0) as u8;
-                let g = ((y as f32 / self.height as f32) * 255.0) as u8;
-                let b = 255;
-                match self.get(x as isize, y as isize) {
-                    Cell::Alive => print!("\x1B[38;2;{r};{g};{b}m█▓\x1B[0m"),
-                    Cell::Dead => print!("  "),
-                }
+    fn population(&self) -> usize {
+        self.cells.population()
+    }
+
+    /// A hash of the cells, which tells generations apart.
+    fn key(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.cells.hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+/// Draws the cells of `pattern` in a frame, replacing the previous one if `clear` is set, and
+/// highlighting the cell under `cursor`.
+#[allow(clippy::print_with_newline)]
+fn print_frame(pattern: &Pattern, clear: bool, cursor: Option<(usize, usize)>) {
+    if clear {
+        print!("\x1B[{}A\x1B[J", pattern.height + 2);
+    }
+    for _ in 0..pattern.width {
+        print!("░░");
+    }
+    print!("░░░░\n");
+    for y in 0..pattern.height {
+        print!("░░");
+        for x in 0..pattern.width {
+            let r = ((x as f32 / pattern.width as f32) * 255.0) as u8;
+            let g = ((y as f32 / pattern.height as f32) * 255.0) as u8;
+            let b = 255;
+            if cursor == Some((x, y)) {
+                print!("\x1B[7m");
+            }
+            match pattern.get(x, y) {
+                Cell::Alive => print!("\x1B[38;2;{r};{g};{b}m█▓\x1B[0m"),
+                Cell::Dead => print!("  "),
+            }
+            if cursor == Some((x, y)) {
+                print!("\x1B[0m");
             }
-            print!("░░\n");
-        }
-        for _ in 0..self.width {
-            print!("░░");
         }
-        println!("░░░░");
+        print!("░░\n");
+    }
+    for _ in 0..pattern.width {
+        print!("░░");
     }
+    println!("░░░░");
 }
 
-fn main() {
-    let mut board = match Board::from_args() {
-        Ok(ok) => ok,
-        Err(err) => {
-            match err {
-                ParseError::InvalidWidth { arg } => {
-                    eprintln!("error: '{arg}' is not a valid width")
+/// The options of the program.
+///
+/// ```text
+/// m02ex07 [--load FILE [--at X,Y]] [--rule B.../S...] [--topology bounded|torus|klein]
+///         [--hashlife [--step N] [--zoom K]] [--seed N] [--generations N]
+///         [--headless | --interactive] [--dump rle|cells] <width> <height> [percentage]
+/// ```
+struct Config {
+    width: usize,
+    height: usize,
+    /// How many of the cells start alive, in percents.
+    percentage: u32,
+    /// A `.rle` or `.cells` file to place on the board.
+    pattern: Option<&'static str>,
+    /// Where to put the top-left corner of the pattern. It is centered by default.
+    position: Option<(usize, usize)>,
+    /// The rule to run. Defaults to the one of the pattern file, or to Conway's.
+    rule: Option<Rule>,
+    topology: Option<Topology>,
+    /// Run on an unbounded plane with HashLife, showing the board as a viewport into it.
+    hashlife: bool,
+    /// How many generations to advance between two frames with HashLife.
+    step: u64,
+    /// With HashLife, each cell on screen stands for `2^zoom` cells per side.
+    zoom: u8,
+    /// The seed of the random cells, picked from the time when not given.
+    seed: u64,
+    /// Stop after this many generations, even if the board is still changing.
+    generations: Option<u64>,
+    /// Print the population of each generation instead of animating the board.
+    headless: bool,
+    /// Control the animation with the keyboard.
+    interactive: bool,
+    /// Only print the last generation, in this format.
+    dump: Option<Format>,
+}
+
+impl Config {
+    fn from_args() -> Result<Self, ParseError> {
+        let mut pattern = None;
+        let mut position = None;
+        let mut dump = None;
+        let mut rule = None;
+        let mut topology = None;
+        let mut hashlife = false;
+        let mut step = 1;
+        let mut zoom = 0;
+        let mut seed = None;
+        let mut generations = None;
+        let mut headless = false;
+        let mut interactive = false;
+        let mut positional = Vec::new();
+
+        let mut args = ftkit::ARGS.into_iter().skip(1);
+        while let Some(arg) = args.next() {
+            let mut value = |option| args.next().ok_or(ParseError::MissingValue { option });
+            match arg {
+                "--load" => pattern = Some(value("--load")?),
+                "--at" => {
+                    let arg = value("--at")?;
+                    let parsed = arg
+                        .split_once(',')
+                        .and_then(|(x, y)| Some((x.parse().ok()?, y.parse().ok()?)));
+                    position = Some(parsed.ok_or(ParseError::InvalidPosition { arg })?);
                 }
-                ParseError::InvalidHeight { arg } => {
-                    eprintln!("error: '{arg}' is not a valid height")
+                "--rule" => {
+                    let arg = value("--rule")?;
+                    rule = Some(arg.parse().map_err(|()| ParseError::InvalidRule { arg })?);
                 }
-                ParseError::InvalidPercentage { arg } => {
-                    eprintln!("error: '{arg}' is not a valid percentage")
+                "--topology" => {
+                    let arg = value("--topology")?;
+                    topology = Some(
+                        arg.parse()
+                            .map_err(|()| ParseError::InvalidTopology { arg })?,
+                    );
                 }
-                ParseError::TooManyArguments => eprintln!("error: too many arguments"),
-                ParseError::NotEnoughArguments => eprintln!("error: not enough arguments"),
+                "--hashlife" => hashlife = true,
+                "--step" => {
+                    let arg = value("--step")?;
+                    step = match arg.parse() {
+                        Ok(ok) if ok > 0 => ok,
+                        _ => return Err(ParseError::InvalidStep { arg }),
+                    };
+                }
+                "--zoom" => {
+                    let arg = value("--zoom")?;
+                    zoom = match arg.parse() {
+                        Ok(ok) if ok < 32 => ok,
+                        _ => return Err(ParseError::InvalidZoom { arg }),
+                    };
+                }
+                "--seed" => {
+                    let arg = value("--seed")?;
+                    seed = Some(arg.parse().map_err(|_| ParseError::InvalidSeed { arg })?);
+                }
+                "--generations" => {
+                    let arg = value("--generations")?;
+                    let parsed = arg.parse();
+                    generations = Some(parsed.map_err(|_| ParseError::InvalidGenerations { arg })?);
+                }
+                "--headless" => headless = true,
+                "--interactive" => interactive = true,
+                "--dump" => {
+                    let arg = value("--dump")?;
+                    dump = Some(
+                        arg.parse()
+                            .map_err(|()| ParseError::InvalidFormat { arg })?,
+                    );
+                }
+                _ if arg.starts_with("--") => return Err(ParseError::UnknownOption { arg }),
+                _ => positional.push(arg),
+            }
+        }
+
+        if hashlife && topology.is_some() {
+            return Err(ParseError::TopologyWithHashLife);
+        }
+        if hashlife {
+            let generations = step.max(generations.unwrap_or(0));
+            if generations > hashlife::MAX_GENERATION {
+                return Err(ParseError::TooFarForHashLife { generations });
             }
-            return;
+        }
+        // Otherwise, runs would go on with an universe that cannot advance anymore.
+        if hashlife && generations.is_none() {
+            generations = Some(hashlife::MAX_GENERATION);
+        }
+        if interactive && (headless || dump.is_some()) {
+            return Err(ParseError::InteractiveWithHeadless);
+        }
+
+        // The percentage is only optional when a pattern fills the board.
+        let (width, height, percentage) = match positional[..] {
+            [width, height] if pattern.is_some() => (width, height, "0"),
+            [width, height, percentage] => (width, height, percentage),
+            [] | [_] | [_, _] => return Err(ParseError::NotEnoughArguments),
+            _ => return Err(ParseError::TooManyArguments),
+        };
+
+        let width = match width.parse() {
+            Ok(ok) => ok,
+            Err(_) => return Err(ParseError::InvalidWidth { arg: width }),
+        };
+
+        let height = match height.parse() {
+            Ok(ok) => ok,
+            Err(_) => return Err(ParseError::InvalidHeight { arg: height }),
+        };
+
+        let percentage = match percentage.parse::<u32>() {
+            Ok(ok) if ok <= 100 => ok,
+            _ => return Err(ParseError::InvalidPercentage { arg: percentage }),
+        };
+
+        Ok(Self {
+            width,
+            height,
+            percentage,
+            pattern,
+            position,
+            rule,
+            topology,
+            hashlife,
+            step,
+            zoom,
+            seed: seed.unwrap_or_else(Random::seed),
+            generations,
+            headless,
+            interactive,
+            dump,
+        })
+    }
+
+    /// Creates the first generation, loading the pattern file if there is one.
+    fn board(&self) -> Result<Board, ParseError> {
+        let mut random = Random::new(self.seed);
+        let mut board = Board::new(self.width, self.height, self.percentage, &mut random);
+        let mut rule = self.rule;
+
+        if let Some(path) = self.pattern {
+            let format = Format::from_path(Path::new(path))
+                .ok_or(ParseError::UnknownPatternFormat { path })?;
+            let text = std::fs::read_to_string(path)
+                .map_err(|error| ParseError::CannotReadPattern { path, error })?;
+            let pattern = Pattern::parse(&text, format)
+                .map_err(|error| ParseError::InvalidPattern { path, error })?;
+
+            let (x, y) = self.position.unwrap_or((
+                self.width.saturating_sub(pattern.width) / 2,
+                self.height.saturating_sub(pattern.height) / 2,
+            ));
+            let fits = |start: usize, len: usize, max: usize| {
+                start.checked_add(len).is_some_and(|end| end <= max)
+            };
+            if !fits(x, pattern.width, self.width) || !fits(y, pattern.height, self.height) {
+                return Err(ParseError::PatternTooLarge { path });
+            }
+            board.place(&pattern, x, y);
+            rule = rule.or(pattern.rule);
+        }
+
+        board.rule = rule.unwrap_or_default();
+        if self.hashlife && board.rule.births_from_nothing() {
+            return Err(ParseError::B0WithHashLife { rule: board.rule });
+        }
+        board.topology = self.topology.unwrap_or_default();
+        Ok(board)
+    }
+}
+
+/// Runs the simulation until it settles or reaches the generation limit, and returns how it
+/// settled.
+///
+/// Frames are animated in the terminal, unless the run is headless, in which case the
+/// population of each generation is printed instead, or nothing if the last generation is
+/// dumped.
+fn run(simulation: &mut impl Simulation, config: &Config) -> Option<Outcome> {
+    let mut detector = CycleDetector::new(simulation.rule());
+    let print_stats = config.headless && config.dump.is_none();
+    let mut previous = simulation.population();
+
+    if print_stats {
+        println!("generation\tpopulation\tchange");
+        println!("{}\t{previous}\t+0", simulation.generation());
+    } else if !config.headless {
+        print_frame(&simulation.frame(), false, None);
+    }
+
+    loop {
+        let generation = simulation.generation();
+        let key = simulation.key();
+        let population = simulation.population();
+        if let Some(outcome) = detector.observe(generation, key, population == 0) {
+            break Some(outcome);
+        }
+
+        let remaining = match config.generations {
+            Some(limit) if generation >= limit => break None,
+            Some(limit) => limit - generation,
+            None => u64::MAX,
+        };
+        if !config.headless {
+            std::thread::sleep(std::time::Duration::from_secs_f32(0.1));
+        }
+        simulation.advance(remaining);
+
+        let population = simulation.population();
+        if print_stats {
+            let change = population as i128 - previous as i128;
+            println!("{}\t{population}\t{change:+}", simulation.generation());
+        } else if !config.headless {
+            print_frame(&simulation.frame(), true, None);
+        }
+        previous = population;
+    }
+}
+
+/// Runs the simulation, and prints its last generation if asked to.
+///
+/// Interactive runs only stop when asked to, so they have no outcome.
+fn run_and_dump(
+    simulation: &mut impl Simulation,
+    config: &Config,
+) -> Result<Option<Outcome>, std::io::Error> {
+    if config.interactive {
+        interactive::run(simulation, config)?;
+        return Ok(None);
+    }
+
+    let outcome = run(simulation, config);
+    if let Some(format) = config.dump {
+        print!("{}", simulation.frame().cropped().write(format));
+    }
+    Ok(outcome)
+}
+
+fn main() -> ExitCode {
+    let result = Config::from_args().and_then(|mut config| {
+        let board = config.board()?;
+        // Dumping the last generation leaves no room for the animation.
+        config.headless |= config.dump.is_some();
+        Ok((config, board))
+    });
+    let (config, mut board) = match result {
+        Ok(ok) => ok,
+        Err(err) => {
+            eprintln!("error: {err}");
+            return ExitCode::FAILURE;
         }
     };
 
-    board.print(false);
+    let outcome = if config.hashlife {
+        let mut viewport = Viewport {
+            universe: Universe::from_pattern(&board.to_pattern(), board.rule),
+            width: board.width,
+            height: board.height,
+            zoom: config.zoom,
+            step: config.step,
+        };
+        run_and_dump(&mut viewport, &config)
+    } else {
+        run_and_dump(&mut board, &config)
+    };
+
+    let mut summary = match outcome {
+        Ok(_) if config.interactive => return ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err}");
+            return ExitCode::FAILURE;
+        }
+        Ok(Some(outcome)) => outcome.to_string(),
+        Ok(None) => format!("stopped at generation {}", config.generations.unwrap_or(0)),
+    };
+    if config.percentage > 0 {
+        summary += &format!(" (seed {})", config.seed);
+    }
+    // Keep the standard output for the statistics or the pattern.
+    if config.headless {
+        eprintln!("{summary}");
+    } else {
+        println!("{summary}");
+    }
+    ExitCode::SUCCESS
+}
+
+#[cfg(test)]
+#[test]
+fn random_cells() {
+    let numbers = |seed| {
+        let mut random = Random::new(seed);
+        [random.next_u64(), random.next_u64()]
+    };
+    assert_eq!(numbers(1), numbers(1));
+    assert_ne!(numbers(1), numbers(2));
+    assert_ne!(numbers(1)[0], numbers(1)[1]);
+
+    let mut random = Random::new(7);
+    for n in [1, 2, 100, u32::MAX] {
+        assert!(random.below(n) < n);
+    }
+
+    for _ in 0..100 {
+        assert_eq!(Cell::random_cell(0, &mut random), Cell::Dead);
+        assert_eq!(Cell::random_cell(100, &mut random), Cell::Alive);
+    }
+    let alive = (0..10_000)
+        .filter(|_| Cell::random_cell(30, &mut random) == Cell::Alive)
+        .count();
+    assert!((2800..3200).contains(&alive), "{alive} cells alive");
+}
+
+#[cfg(test)]
+fn board_with(width: usize, height: usize, rle: &str, x: usize, y: usize) -> Board {
+    let mut board = Board::empty(width, height);
+    board.place(&Pattern::parse_rle(rle).unwrap(), x, y);
+    board
+}
+
+#[cfg(test)]
+#[test]
+fn glider_moves() {
+    let glider = "x = 3, y = 3\nbob$2bo$3o!";
+    let mut board = board_with(10, 10, glider, 1, 1);
+    for _ in 0..4 {
+        board.step();
+    }
+    // After four generations, a glider is back to its shape, one cell down and right.
+    assert_eq!(
+        board.to_pattern(),
+        board_with(10, 10, glider, 2, 2).to_pattern()
+    );
+}
+
+#[cfg(test)]
+#[test]
+fn glider_gun() {
+    let gun = "x = 36, y = 9\n24bo$22bobo$12b2o6b2o12b2o$11bo3bo4b2o12b2o$2o8bo5bo3b2o$2o8bo3b\
+               ob2o4bobo$10bo5bo7bo$11bo3bo$12b2o!";
+    let mut board = board_with(60, 40, gun, 1, 1);
+    let initial = board.to_pattern();
+    for _ in 0..30 {
+        board.step();
+    }
+    // The gun has a period of 30 generations, and emits one glider per period.
+    let pattern = board.to_pattern();
+    assert_eq!(pattern.population(), initial.population() + 5);
+    for (now, before) in pattern.cells.iter().zip(&initial.cells) {
+        assert!(now.is_alive() || before.is_dead());
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn highlife_replicator() {
+    let replicator = "x = 5, y = 5, rule = B36/S23\n2b3o$bo2bo$o3bo$o2bo$3o!";
+    let mut board = board_with(40, 40, replicator, 17, 17);
+    board.rule = "highlife".parse().unwrap();
+    for _ in 0..12 {
+        board.step();
+    }
+    // Twelve generations later, the replicator has made a copy of itself along its diagonal.
+    let mut expected = board_with(40, 40, replicator, 15, 15);
+    expected.place(&Pattern::parse_rle(replicator).unwrap(), 19, 19);
+    assert_eq!(board.cells, expected.cells);
+
+    // Conway's rule destroys it.
+    let mut board = board_with(40, 40, replicator, 17, 17);
+    for _ in 0..12 {
+        board.step();
+    }
+    assert_ne!(board.cells, expected.cells);
+}
+
+#[cfg(test)]
+#[test]
+fn seeds_and_day_night() {
+    // In Seeds, no cell ever survives.
+    let mut board = Board::new(30, 30, 40, &mut Random::new(1));
+    board.rule = "B2/S".parse().unwrap();
+    let before = board.to_pattern();
+    board.step();
+    let now = board.to_pattern();
+    assert!(now.cells.iter().any(|cell| cell.is_alive()));
+    for (now, before) in now.cells.iter().zip(&before.cells) {
+        assert!(!(now.is_alive() && before.is_alive()));
+    }
+
+    // Day & Night is symmetric: inverting a board commutes with stepping it.
+    let invert = |board: &Board| -> Vec<Cell> {
+        board
+            .to_pattern()
+            .cells
+            .iter()
+            .map(|&cell| match cell {
+                Cell::Alive => Cell::Dead,
+                Cell::Dead => Cell::Alive,
+            })
+            .collect()
+    };
+    let mut board = Board::new(30, 30, 50, &mut Random::new(2));
+    board.rule = "B3678/S34678".parse().unwrap();
+    let mut inverted = Board::empty(30, 30);
+    inverted.rule = board.rule;
+    for (i, cell) in invert(&board).into_iter().enumerate() {
+        inverted.cells.set(i % 30, i / 30, cell);
+    }
+    for _ in 0..10 {
+        board.step();
+        inverted.step();
+        assert_eq!(inverted.to_pattern().cells, invert(&board));
+    }
+}
+
+#[cfg(test)]
+fn live_cells(board: &Board) -> Vec<(usize, usize)> {
+    let cells = board.to_pattern().cells;
+    let mut cells: Vec<_> = (0..cells.len())
+        .filter(|&i| cells[i].is_alive())
+        .map(|i| (i % board.width, i / board.width))
+        .collect();
+    cells.sort();
+    cells
+}
+
+#[cfg(test)]
+#[test]
+fn glider_on_torus() {
+    let glider = "x = 3, y = 3\nbob$2bo$3o!";
+    let mut board = board_with(8, 8, glider, 4, 4);
+    let start = live_cells(&board);
+    for generation in 1..=32 {
+        board.step();
+        assert_eq!(live_cells(&board).len(), 5, "generation {generation}");
+    }
+    // The glider went through both edges and is back where it started.
+    assert_eq!(live_cells(&board), start);
+}
+
+#[cfg(test)]
+#[test]
+fn glider_on_bounded_plane() {
+    let glider = "x = 3, y = 3\nbob$2bo$3o!";
+    let mut board = board_with(10, 10, glider, 1, 1);
+    board.topology = Topology::Bounded;
+    for _ in 0..40 {
+        board.step();
+    }
+    // The glider crashes into the corner and settles as a block, instead of coming back.
+    assert_eq!(live_cells(&board), [(8, 8), (8, 9), (9, 8), (9, 9)]);
+}
+
+#[cfg(test)]
+#[test]
+fn glider_on_klein_bottle() {
+    let (width, height) = (12, 8);
+    let glider = "x = 3, y = 3\nbob$2bo$3o!";
+    let mut board = board_with(width, height, glider, 6, 2);
+    board.topology = Topology::Klein;
+    let start = live_cells(&board);
+    for _ in 0..4 * height {
+        board.step();
+    }
+
+    // On the plane, the glider would have moved `height` cells right and down. Going through
+    // the bottom edge once mirrored it, so it now travels left instead.
+    let mut expected: Vec<_> = start
+        .iter()
+        .map(|&(x, y)| {
+            let x = (width as isize - 1 - (x + height) as isize).rem_euclid(width as isize);
+            (x as usize, y)
+        })
+        .collect();
+    expected.sort();
+    assert_eq!(live_cells(&board), expected);
+
+    // A torus would not have mirrored it.
+    let mut board = board_with(width, height, glider, 6, 2);
+    for _ in 0..4 * height {
+        board.step();
+    }
+    assert_ne!(live_cells(&board), expected);
+}
+
+#[cfg(test)]
+fn settle(board: &mut Board) -> Outcome {
+    let mut detector = CycleDetector::new(board.rule);
     loop {
-        std::thread::sleep(std::time::Duration::from_secs_f32(0.1));
+        let is_empty = board.population() == 0;
+        if let Some(outcome) = detector.observe(board.generation, board.key(), is_empty) {
+            break outcome;
+        }
         board.step();
-        board.print(true);
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn settling() {
+    // A blinker.
+    let mut board = board_with(5, 5, "x = 3, y = 1\n3o!", 1, 2);
+    assert_eq!(
+        settle(&mut board),
+        Outcome::Cycle {
+            start: 0,
+            period: 2
+        }
+    );
+
+    // Three cells of a block, which grows the fourth one.
+    let mut board = board_with(6, 6, "x = 2, y = 2\n2o$o!", 2, 2);
+    assert_eq!(
+        settle(&mut board),
+        Outcome::Cycle {
+            start: 1,
+            period: 1
+        }
+    );
+
+    // A lonely cell.
+    let mut board = board_with(6, 6, "x = 1, y = 1\no!", 2, 2);
+    assert_eq!(settle(&mut board), Outcome::Extinct { generation: 1 });
+
+    // Diehard vanishes after 130 generations.
+    let mut board = board_with(60, 60, "x = 8, y = 3\n6bo$2o$bo3b3o!", 26, 28);
+    board.topology = Topology::Bounded;
+    assert_eq!(settle(&mut board), Outcome::Extinct { generation: 130 });
+
+    // Under B0/S8, the empty torus fills up and stays full.
+    let mut board = Board::empty(4, 4);
+    board.rule = "B0/S8".parse().unwrap();
+    assert_eq!(
+        settle(&mut board),
+        Outcome::Cycle {
+            start: 1,
+            period: 1
+        }
+    );
+
+    // A glider on a torus comes back where it started.
+    let mut board = board_with(8, 8, "x = 3, y = 3\nbob$2bo$3o!", 0, 0);
+    assert_eq!(
+        settle(&mut board),
+        Outcome::Cycle {
+            start: 0,
+            period: 32
+        }
+    );
+}
+
+#[cfg(test)]
+fn headless(width: usize, height: usize, generations: Option<u64>) -> Config {
+    Config {
+        width,
+        height,
+        percentage: 0,
+        pattern: None,
+        position: None,
+        rule: None,
+        topology: None,
+        hashlife: false,
+        step: 1,
+        zoom: 0,
+        seed: 0,
+        generations,
+        headless: true,
+        interactive: false,
+        dump: Some(Format::Cells),
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn seeded_boards() {
+    let board = |seed| Board::new(20, 20, 30, &mut Random::new(seed)).cells;
+    assert_eq!(board(42), board(42));
+    assert_ne!(board(42), board(43));
+
+    let population = Board::new(100, 100, 30, &mut Random::new(0)).population();
+    assert!((2700..3300).contains(&population), "{population}");
+}
+
+#[cfg(test)]
+#[test]
+fn generation_limit() {
+    // A glider on a large torus does not come back before the limit.
+    let config = headless(40, 40, Some(10));
+    let mut board = board_with(40, 40, "x = 3, y = 3\nbob$2bo$3o!", 0, 0);
+    assert_eq!(run(&mut board, &config), None);
+    assert_eq!(board.generation, 10);
+
+    // The HashLife backend stops at the limit too, even with larger steps.
+    let glider = Pattern::parse_rle("x = 3, y = 3\nbob$2bo$3o!").unwrap();
+    let mut viewport = Viewport {
+        universe: Universe::from_pattern(&glider, Rule::CONWAY),
+        width: 10,
+        height: 10,
+        zoom: 0,
+        step: 64,
+    };
+    let config = headless(10, 10, Some(1000));
+    assert_eq!(run(&mut viewport, &config), None);
+    assert_eq!(viewport.generation(), 1000);
+
+    // Settling comes first.
+    let config = headless(5, 5, Some(10));
+    let mut board = board_with(5, 5, "x = 3, y = 1\n3o!", 1, 2);
+    assert_eq!(
+        run(&mut board, &config),
+        Some(Outcome::Cycle {
+            start: 0,
+            period: 2
+        })
+    );
+}
+
+#[cfg(test)]
+#[test]
+fn editing() {
+    let mut board = Board::empty(4, 3);
+    Simulation::toggle(&mut board, 1, 2);
+    assert_eq!(live_cells(&board), [(1, 2)]);
+    Simulation::toggle(&mut board, 1, 2);
+    assert_eq!(live_cells(&board), []);
+
+    board.randomize(50, &mut Random::new(3));
+    let population = board.population();
+    assert!(population > 0 && population < 12);
+    board.clear();
+    assert_eq!(board.population(), 0);
+
+    // On HashLife, edits go to the top-left cell of each square on screen.
+    let mut viewport = Viewport {
+        universe: Universe::new(Rule::CONWAY),
+        width: 4,
+        height: 4,
+        zoom: 1,
+        step: 1,
+    };
+    viewport.toggle(3, 0);
+    assert_eq!(viewport.population(), 1);
+    assert_eq!(viewport.frame().get(3, 0), Cell::Alive);
+    viewport.randomize(100, &mut Random::new(3));
+    assert_eq!(viewport.population(), 16);
+    viewport.clear();
+    assert_eq!(viewport.population(), 0);
+}
+
+#[cfg(test)]
+#[test]
+fn same_as_one_by_one() {
+    let mut random = Random::new(5);
+    let rules = [
+        "B3/S23",
+        "B36/S23",
+        "B2/S",
+        "B3678/S34678",
+        "B0/S8",
+        "B012345678/S",
+    ];
+    for (width, height) in [
+        (1, 1),
+        (1, 5),
+        (5, 1),
+        (2, 2),
+        (63, 7),
+        (64, 9),
+        (65, 3),
+        (130, 40),
+    ] {
+        for topology in [Topology::Bounded, Topology::Torus, Topology::Klein] {
+            for rule in rules {
+                let mut board = Board::new(width, height, 40, &mut random);
+                board.rule = rule.parse().unwrap();
+                board.topology = topology;
+                let mut expected = Board::new(width, height, 0, &mut random);
+                expected.cells = board.cells.clone();
+                expected.rule = board.rule;
+                expected.topology = topology;
+                // Use as many threads as rows, and more.
+                board.threads = height + 1;
+
+                for generation in 0..8 {
+                    board.step();
+                    expected.step_one_by_one();
+                    assert_eq!(
+                        board.cells, expected.cells,
+                        "{width}x{height}, {topology:?}, {rule}, generation {generation}"
+                    );
+                }
+                board.threads = 1;
+                board.step();
+                expected.step_one_by_one();
+                assert_eq!(board.cells, expected.cells);
+            }
+        }
     }
 }
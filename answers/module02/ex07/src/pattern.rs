@@ -0,0 +1,472 @@
+//! Reading and writing patterns in the RLE and plaintext (`.cells`) formats.
+//!
+//! See <https://conwaylife.com/wiki/Run_Length_Encoded> and
+//! <https://conwaylife.com/wiki/Plaintext>.
+
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::rule::Rule;
+use crate::Cell;
+
+/// The largest number of cells an RLE header may declare, so that a bad file cannot make us
+/// allocate all the memory there is.
+pub const MAX_CELLS: usize = 1 << 30;
+
+/// The file formats a pattern can be stored in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Rle,
+    Cells,
+}
+
+impl Format {
+    /// Guesses the format of a file from its extension.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        path.extension()?.to_str()?.parse().ok()
+    }
+}
+
+impl FromStr for Format {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "rle" => Ok(Self::Rle),
+            "cells" => Ok(Self::Cells),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PatternError {
+    /// The `x = ..., y = ...` line of an RLE file is missing or malformed, or has an invalid
+    /// rule.
+    InvalidHeader { line: usize },
+    /// A character that has no meaning in the format. Lines and columns start at 1.
+    UnexpectedCharacter {
+        character: char,
+        line: usize,
+        column: usize,
+    },
+    /// The cells of an RLE file go past the size declared in its header.
+    OutOfBounds { line: usize },
+    /// The header of an RLE file declares more than [`MAX_CELLS`] cells.
+    TooLarge { line: usize },
+}
+
+impl fmt::Display for PatternError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidHeader { line } => {
+                write!(f, "line {line}: invalid `x = .., y = ..` header")
+            }
+            Self::UnexpectedCharacter {
+                character,
+                line,
+                column,
+            } => write!(f, "line {line}, column {column}: unexpected {character:?}"),
+            Self::OutOfBounds { line } => {
+                write!(f, "line {line}: the pattern is larger than its header says")
+            }
+            Self::TooLarge { line } => {
+                write!(
+                    f,
+                    "line {line}: the pattern has more than {MAX_CELLS} cells"
+                )
+            }
+        }
+    }
+}
+
+/// A rectangle of cells.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pattern {
+    pub width: usize,
+    pub height: usize,
+    /// The cells, row by row.
+    pub cells: Vec<Cell>,
+    /// The rule the pattern is meant to run under, when the file says so.
+    pub rule: Option<Rule>,
+}
+
+impl Pattern {
+    /// Creates a pattern made of dead cells only.
+    pub fn empty(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            cells: vec![Cell::Dead; width * height],
+            rule: None,
+        }
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Cell {
+        self.cells[x + y * self.width]
+    }
+
+    /// The number of live cells.
+    #[allow(dead_code)]
+    pub fn population(&self) -> usize {
+        self.cells.iter().filter(|cell| cell.is_alive()).count()
+    }
+
+    pub fn parse(s: &str, format: Format) -> Result<Self, PatternError> {
+        match format {
+            Format::Rle => Self::parse_rle(s),
+            Format::Cells => Self::parse_cells(s),
+        }
+    }
+
+    /// Parses a plaintext pattern: `.` is a dead cell, `O` a live one, and lines starting with
+    /// `!` are comments. Lines may be shorter than the widest one.
+    pub fn parse_cells(s: &str) -> Result<Self, PatternError> {
+        let mut rows = Vec::new();
+        for (index, line) in s.lines().enumerate() {
+            if line.starts_with('!') {
+                continue;
+            }
+            let mut row = Vec::new();
+            for (column, character) in line.trim_end().chars().enumerate() {
+                row.push(match character {
+                    '.' => Cell::Dead,
+                    'O' | '*' => Cell::Alive,
+                    _ => {
+                        return Err(PatternError::UnexpectedCharacter {
+                            character,
+                            line: index + 1,
+                            column: column + 1,
+                        })
+                    }
+                });
+            }
+            rows.push(row);
+        }
+
+        let width = rows.iter().map(Vec::len).max().unwrap_or(0);
+        let mut pattern = Self::empty(width, rows.len());
+        for (y, row) in rows.iter().enumerate() {
+            pattern.cells[y * width..][..row.len()].copy_from_slice(row);
+        }
+        Ok(pattern)
+    }
+
+    /// Parses a run-length encoded pattern.
+    ///
+    /// Lines starting with `#` are comments. The first other line is the `x = ..., y = ...`
+    /// header, and the rest is made of `b` (dead), `o` (alive) and `$` (end of row) tags, each
+    /// optionally preceded by a repeat count, until a final `!`.
+    pub fn parse_rle(s: &str) -> Result<Self, PatternError> {
+        let mut lines = s
+            .lines()
+            .enumerate()
+            .map(|(index, line)| (index + 1, line))
+            .filter(|(_, line)| !line.starts_with('#'));
+
+        let (header_line, header) = lines
+            .next()
+            .ok_or(PatternError::InvalidHeader { line: 1 })?;
+        let (width, height, rule) =
+            parse_rle_header(header).ok_or(PatternError::InvalidHeader { line: header_line })?;
+        if width
+            .checked_mul(height)
+            .is_none_or(|cells| cells > MAX_CELLS)
+        {
+            return Err(PatternError::TooLarge { line: header_line });
+        }
+
+        let mut pattern = Self::empty(width, height);
+        pattern.rule = rule;
+        let (mut x, mut y): (usize, usize) = (0, 0);
+        let mut count: Option<usize> = None;
+
+        'lines: for (line, text) in lines {
+            for (column, character) in text.chars().enumerate() {
+                let run = count.unwrap_or(1);
+                // Runs that overflow are necessarily out of bounds.
+                let out_of_bounds = PatternError::OutOfBounds { line };
+                match character {
+                    '0'..='9' => {
+                        let digit = character as usize - '0' as usize;
+                        count = Some(
+                            count
+                                .unwrap_or(0)
+                                .checked_mul(10)
+                                .and_then(|count| count.checked_add(digit))
+                                .ok_or(out_of_bounds)?,
+                        );
+                        continue;
+                    }
+                    'b' => x = x.checked_add(run).ok_or(out_of_bounds)?,
+                    'o' => {
+                        let end = x.checked_add(run).ok_or(out_of_bounds.clone())?;
+                        if end > width || y >= height {
+                            return Err(out_of_bounds);
+                        }
+                        pattern.cells[y * width + x..][..run].fill(Cell::Alive);
+                        x = end;
+                    }
+                    '$' => {
+                        x = 0;
+                        y = y.checked_add(run).ok_or(out_of_bounds)?;
+                    }
+                    '!' => break 'lines,
+                    c if c.is_whitespace() => (),
+                    _ => {
+                        return Err(PatternError::UnexpectedCharacter {
+                            character,
+                            line,
+                            column: column + 1,
+                        })
+                    }
+                }
+                count = None;
+            }
+        }
+
+        Ok(pattern)
+    }
+
+    /// Returns the smallest part of the pattern that contains all of its live cells.
+    pub fn cropped(&self) -> Self {
+        let alive = |x: usize, y: usize| self.get(x, y).is_alive();
+        let rows: Vec<usize> = (0..self.height)
+            .filter(|&y| (0..self.width).any(|x| alive(x, y)))
+            .collect();
+        let columns: Vec<usize> = (0..self.width)
+            .filter(|&x| (0..self.height).any(|y| alive(x, y)))
+            .collect();
+        let (Some(&top), Some(&bottom), Some(&left), Some(&right)) =
+            (rows.first(), rows.last(), columns.first(), columns.last())
+        else {
+            return Self {
+                rule: self.rule,
+                ..Self::empty(0, 0)
+            };
+        };
+
+        let mut pattern = Self::empty(right - left + 1, bottom - top + 1);
+        pattern.rule = self.rule;
+        for y in 0..pattern.height {
+            for x in 0..pattern.width {
+                pattern.cells[x + y * pattern.width] = self.get(left + x, top + y);
+            }
+        }
+        pattern
+    }
+
+    pub fn write(&self, format: Format) -> String {
+        match format {
+            Format::Rle => self.to_rle(),
+            Format::Cells => self.to_cells(),
+        }
+    }
+
+    /// Writes the pattern in the plaintext format, without trailing dead cells.
+    pub fn to_cells(&self) -> String {
+        let mut result = String::new();
+        for y in 0..self.height {
+            let row: String = (0..self.width)
+                .map(|x| if self.get(x, y).is_alive() { 'O' } else { '.' })
+                .collect();
+            result.push_str(row.trim_end_matches('.'));
+            result.push('\n');
+        }
+        result
+    }
+
+    /// Writes the pattern in the RLE format, with lines of at most 70 characters.
+    pub fn to_rle(&self) -> String {
+        // The tags of the pattern, with their repeat count.
+        let mut tags: Vec<(usize, char)> = Vec::new();
+        let mut push = |count: usize, tag: char| match tags.last_mut() {
+            Some((last_count, last_tag)) if *last_tag == tag => *last_count += count,
+            _ => tags.push((count, tag)),
+        };
+        for y in 0..self.height {
+            if y != 0 {
+                push(1, '$');
+            }
+            // Trailing dead cells are implied by the end of the row.
+            let end = (0..self.width)
+                .rposition(|x| self.get(x, y).is_alive())
+                .map_or(0, |x| x + 1);
+            for x in 0..end {
+                push(1, if self.get(x, y).is_alive() { 'o' } else { 'b' });
+            }
+        }
+        // So are empty rows at the bottom of the pattern.
+        if let Some((_, '$')) = tags.last() {
+            tags.pop();
+        }
+        tags.push((1, '!'));
+
+        let mut result = format!("x = {}, y = {}", self.width, self.height);
+        if let Some(rule) = self.rule {
+            result.push_str(&format!(", rule = {rule}"));
+        }
+        result.push('\n');
+        let mut line = String::new();
+        for (count, tag) in tags {
+            let item = match count {
+                1 => tag.to_string(),
+                _ => format!("{count}{tag}"),
+            };
+            if line.len() + item.len() > 70 {
+                result.push_str(&line);
+                result.push('\n');
+                line.clear();
+            }
+            line.push_str(&item);
+        }
+        result.push_str(&line);
+        result.push('\n');
+        result
+    }
+}
+
+/// Parses the `x = 3, y = 3, rule = B3/S23` header of an RLE file, returning its width,
+/// height and rule.
+fn parse_rle_header(line: &str) -> Option<(usize, usize, Option<Rule>)> {
+    let mut width = None;
+    let mut height = None;
+    let mut rule = None;
+    for item in line.split(',') {
+        let (key, value) = item.split_once('=')?;
+        match key.trim() {
+            "x" => width = Some(value.trim().parse().ok()?),
+            "y" => height = Some(value.trim().parse().ok()?),
+            "rule" => rule = Some(value.trim().parse().ok()?),
+            _ => (),
+        }
+    }
+    Some((width?, height?, rule))
+}
+
+#[cfg(test)]
+const GLIDER_RLE: &str = "#N Glider\nx = 3, y = 3, rule = B3/S23\nbob$2bo$3o!\n";
+
+#[cfg(test)]
+#[test]
+fn parse_glider() {
+    let glider = Pattern::parse_rle(GLIDER_RLE).unwrap();
+    let cells = Pattern::parse_cells("!Name: Glider\n.O\n..O\nOOO\n").unwrap();
+    assert_eq!(glider.rule, Some(Rule::CONWAY));
+    assert_eq!(cells.rule, None);
+    assert_eq!(glider.cells, cells.cells);
+    assert_eq!((glider.width, glider.height), (3, 3));
+    assert_eq!(glider.population(), 5);
+    assert_eq!(glider.to_cells(), ".O\n..O\nOOO\n");
+}
+
+#[cfg(test)]
+#[test]
+fn roundtrip() {
+    let gun = "x = 36, y = 9, rule = B3/S23\n\
+               24bo$22bobo$12b2o6b2o12b2o$11bo3bo4b2o12b2o$2o8bo5bo3b2o$2o8bo3bob2o4b\n\
+               obo$10bo5bo7bo$11bo3bo$12b2o!\n";
+    let pattern = Pattern::parse_rle(gun).unwrap();
+    assert_eq!(pattern.population(), 36);
+    assert_eq!(pattern.to_rle(), gun);
+    assert_eq!(
+        Pattern::parse_cells(&pattern.to_cells()).unwrap().cells,
+        pattern.cells
+    );
+
+    // Empty rows are encoded as a repeated `$`, and trailing ones are dropped.
+    let mut sparse = Pattern::empty(4, 5);
+    sparse.cells[0] = Cell::Alive;
+    sparse.cells[3 + 3 * 4] = Cell::Alive;
+    assert_eq!(sparse.to_rle(), "x = 4, y = 5\no3$3bo!\n");
+    assert_eq!(Pattern::parse_rle(&sparse.to_rle()).unwrap(), sparse);
+}
+
+#[cfg(test)]
+#[test]
+fn cropped() {
+    let mut pattern = Pattern::empty(10, 10);
+    pattern.cells[3 + 2 * 10] = Cell::Alive;
+    pattern.cells[5 + 6 * 10] = Cell::Alive;
+    let cropped = pattern.cropped();
+    assert_eq!((cropped.width, cropped.height), (3, 5));
+    assert_eq!(cropped.to_cells(), "O\n\n\n\n..O\n");
+    assert_eq!(Pattern::empty(4, 4).cropped(), Pattern::empty(0, 0));
+}
+
+#[cfg(test)]
+#[test]
+fn errors() {
+    assert_eq!(
+        Pattern::parse_rle("#C only comments\n"),
+        Err(PatternError::InvalidHeader { line: 1 })
+    );
+    assert_eq!(
+        Pattern::parse_rle("#C comment\nx = 3\nooo!"),
+        Err(PatternError::InvalidHeader { line: 2 })
+    );
+    assert_eq!(
+        Pattern::parse_rle("x = 1, y = 1, rule = B9/S\no!"),
+        Err(PatternError::InvalidHeader { line: 1 })
+    );
+    assert_eq!(
+        Pattern::parse_rle("x = 2, y = 1\n3o!"),
+        Err(PatternError::OutOfBounds { line: 2 })
+    );
+    assert_eq!(
+        Pattern::parse_rle("x = 10, y = 10\n18446744073709551615bo!"),
+        Err(PatternError::OutOfBounds { line: 2 })
+    );
+    assert_eq!(
+        Pattern::parse_rle("x = 10, y = 10\n99999999999999999999999o!"),
+        Err(PatternError::OutOfBounds { line: 2 })
+    );
+    assert_eq!(
+        Pattern::parse_rle("x = 10, y = 10\n18446744073709551615$18446744073709551615$o!"),
+        Err(PatternError::OutOfBounds { line: 2 })
+    );
+    assert_eq!(
+        Pattern::parse_rle("x = 100000, y = 100000\no!"),
+        Err(PatternError::TooLarge { line: 1 })
+    );
+    assert_eq!(
+        Pattern::parse_rle("x = 18446744073709551615, y = 2\no!"),
+        Err(PatternError::TooLarge { line: 1 })
+    );
+    assert_eq!(
+        Pattern::parse_rle("x = 2, y = 2\nob$\nbq!")
+            .unwrap_err()
+            .to_string(),
+        "line 3, column 2: unexpected 'q'"
+    );
+    // Columns count characters, not bytes.
+    assert_eq!(
+        Pattern::parse_rle("x = 2, y = 2\nbo é!"),
+        Err(PatternError::UnexpectedCharacter {
+            character: 'é',
+            line: 2,
+            column: 4
+        })
+    );
+    assert_eq!(
+        Pattern::parse_rle("x = 2, y = 2\nbo\u{a0}\u{a0}q!")
+            .unwrap_err()
+            .to_string(),
+        "line 2, column 5: unexpected 'q'"
+    );
+    assert_eq!(
+        Pattern::parse_cells("..O\n.#.\n"),
+        Err(PatternError::UnexpectedCharacter {
+            character: '#',
+            line: 2,
+            column: 2
+        })
+    );
+    assert_eq!(Format::from_path(Path::new("gun.rle")), Some(Format::Rle));
+    assert_eq!(
+        Format::from_path(Path::new("a/b.cells")),
+        Some(Format::Cells)
+    );
+    assert_eq!(Format::from_path(Path::new("gun.txt")), None);
+}
@@ -0,0 +1,150 @@
+//! Life-like rules, written in the `B.../S...` notation.
+//!
+//! See <https://conwaylife.com/wiki/Rulestring>.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::Cell;
+
+/// Which neighbor counts give birth to a dead cell, and which let a live cell survive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rule {
+    /// Bit `n` is set when a dead cell with `n` live neighbors becomes alive.
+    birth: u16,
+    /// Bit `n` is set when a live cell with `n` live neighbors stays alive.
+    survival: u16,
+}
+
+impl Rule {
+    /// Conway's Game of Life, B3/S23.
+    pub const CONWAY: Self = Self {
+        birth: 1 << 3,
+        survival: 1 << 2 | 1 << 3,
+    };
+
+    /// Rules that can be given by name instead of their rulestring.
+    const NAMED: [(&'static str, &'static str); 5] = [
+        ("life", "B3/S23"),
+        ("highlife", "B36/S23"),
+        ("seeds", "B2/S"),
+        ("daynight", "B3678/S34678"),
+        ("lifewithoutdeath", "B3/S012345678"),
+    ];
+
+    /// Returns the next state of a cell with `neighbors` live neighbors.
+    pub fn next(self, cell: Cell, neighbors: u32) -> Cell {
+        let mask = match cell {
+            Cell::Alive => self.survival,
+            Cell::Dead => self.birth,
+        };
+        if mask & 1 << neighbors != 0 {
+            Cell::Alive
+        } else {
+            Cell::Dead
+        }
+    }
+
+    /// Whether dead cells with no live neighbors are born (a `B0` rule), so that empty space
+    /// does not stay empty.
+    pub fn births_from_nothing(self) -> bool {
+        self.birth & 1 != 0
+    }
+}
+
+impl Default for Rule {
+    fn default() -> Self {
+        Self::CONWAY
+    }
+}
+
+/// Parses the neighbor counts of one half of a rulestring, such as the `36` of `B36`.
+fn parse_counts(digits: &str) -> Option<u16> {
+    let mut mask = 0u16;
+    for c in digits.chars() {
+        let n = c.to_digit(10).filter(|&n| n <= 8)?;
+        if mask & 1 << n != 0 {
+            return None;
+        }
+        mask |= 1 << n;
+    }
+    Some(mask)
+}
+
+impl FromStr for Rule {
+    type Err = ();
+
+    /// Parses `B3/S23`-style rulestrings (in any order and case), or one of the named rules.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some((_, rule)) = Self::NAMED
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(s))
+        {
+            return rule.parse();
+        }
+
+        let (first, second) = s.split_once('/').ok_or(())?;
+        let mut birth = None;
+        let mut survival = None;
+        for part in [first, second] {
+            let mut chars = part.chars();
+            let half = match chars.next() {
+                Some('B' | 'b') => &mut birth,
+                Some('S' | 's') => &mut survival,
+                _ => return Err(()),
+            };
+            if half.is_some() {
+                return Err(());
+            }
+            *half = Some(parse_counts(chars.as_str()).ok_or(())?);
+        }
+
+        Ok(Self {
+            birth: birth.ok_or(())?,
+            survival: survival.ok_or(())?,
+        })
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let counts = |mask: u16| -> String {
+            (0..=8)
+                .filter(|n| mask & 1 << n != 0)
+                .map(|n| char::from(b'0' + n))
+                .collect()
+        };
+        write!(f, "B{}/S{}", counts(self.birth), counts(self.survival))
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn parse_and_display() {
+    let rule: Rule = "B36/S23".parse().unwrap();
+    assert_eq!(rule.to_string(), "B36/S23");
+    assert_eq!("s23/b63".parse(), Ok(rule));
+    assert_eq!("HighLife".parse(), Ok(rule));
+    assert_eq!("B3/S23".parse(), Ok(Rule::CONWAY));
+    assert_eq!("seeds".parse::<Rule>().unwrap().to_string(), "B2/S");
+    assert_eq!(Rule::default(), Rule::CONWAY);
+
+    for invalid in [
+        "", "B3", "B3/S2/S3", "B3/B2", "B9/S23", "B33/S23", "B3/X23", "3/23",
+    ] {
+        assert_eq!(invalid.parse::<Rule>(), Err(()), "{invalid}");
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn next() {
+    let rule = Rule::CONWAY;
+    assert_eq!(rule.next(Cell::Dead, 3), Cell::Alive);
+    assert_eq!(rule.next(Cell::Dead, 2), Cell::Dead);
+    assert_eq!(rule.next(Cell::Alive, 2), Cell::Alive);
+    assert_eq!(rule.next(Cell::Alive, 4), Cell::Dead);
+    assert!(!rule.births_from_nothing());
+    assert!("B0/S8".parse::<Rule>().unwrap().births_from_nothing());
+    assert_eq!(rule.next(Cell::Alive, 8), Cell::Dead);
+}
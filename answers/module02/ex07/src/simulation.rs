@@ -0,0 +1,164 @@
+//! The two ways of running the Game of Life, behind a common interface.
+
+use crate::hashlife::{Universe, MAX_GENERATION};
+use crate::pattern::Pattern;
+use crate::rule::Rule;
+use crate::{Board, Cell, Random};
+
+pub trait Simulation {
+    fn generation(&self) -> u64;
+
+    fn population(&self) -> u128;
+
+    fn rule(&self) -> Rule;
+
+    /// Identifies the current generation, for the [`CycleDetector`](crate::cycle::CycleDetector).
+    fn key(&mut self) -> u64;
+
+    /// Advances to the next frame, but by no more than `limit` generations.
+    fn advance(&mut self, limit: u64);
+
+    /// The cells to show on screen.
+    fn frame(&self) -> Pattern;
+
+    /// Flips the cell shown at `(x, y)` on screen.
+    fn toggle(&mut self, x: usize, y: usize);
+
+    /// Kills every cell.
+    fn clear(&mut self);
+
+    /// Replaces the cells shown on screen with random ones.
+    fn randomize(&mut self, percentage: u32, random: &mut Random);
+}
+
+fn flip(cell: Cell) -> Cell {
+    match cell {
+        Cell::Alive => Cell::Dead,
+        Cell::Dead => Cell::Alive,
+    }
+}
+
+impl Simulation for Board {
+    fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    fn population(&self) -> u128 {
+        self.population() as u128
+    }
+
+    fn rule(&self) -> Rule {
+        self.rule
+    }
+
+    fn key(&mut self) -> u64 {
+        Board::key(self)
+    }
+
+    fn advance(&mut self, _limit: u64) {
+        self.step();
+    }
+
+    fn frame(&self) -> Pattern {
+        self.to_pattern()
+    }
+
+    fn toggle(&mut self, x: usize, y: usize) {
+        let cell = self.cells.get(x, y);
+        self.cells.set(x, y, flip(cell));
+    }
+
+    fn clear(&mut self) {
+        self.cells.clear();
+    }
+
+    fn randomize(&mut self, percentage: u32, random: &mut Random) {
+        for y in 0..self.height {
+            for x in 0..self.width {
+                self.cells.set(x, y, Cell::random_cell(percentage, random));
+            }
+        }
+    }
+}
+
+/// A HashLife universe, seen through a rectangle centered on the origin.
+pub struct Viewport {
+    pub universe: Universe,
+    pub width: usize,
+    pub height: usize,
+    /// Each cell on screen stands for `2^zoom` cells per side.
+    pub zoom: u8,
+    /// How many generations each frame advances.
+    pub step: u64,
+}
+
+impl Simulation for Viewport {
+    fn generation(&self) -> u64 {
+        self.universe.generation()
+    }
+
+    fn population(&self) -> u128 {
+        self.universe.population()
+    }
+
+    fn rule(&self) -> Rule {
+        self.universe.rule()
+    }
+
+    fn key(&mut self) -> u64 {
+        self.universe.key()
+    }
+
+    /// Stops at [`MAX_GENERATION`], where the universe cannot go further.
+    fn advance(&mut self, limit: u64) {
+        let left = MAX_GENERATION - self.universe.generation();
+        self.universe.step(self.step.min(limit).min(left));
+    }
+
+    fn frame(&self) -> Pattern {
+        let (left, top) = self.origin();
+        self.universe
+            .viewport(left, top, self.width, self.height, self.zoom)
+    }
+
+    /// Flips the top-left cell of the square shown at `(x, y)`.
+    fn toggle(&mut self, x: usize, y: usize) {
+        let (x, y) = self.to_universe(x, y);
+        let cell = self.universe.get(x, y);
+        self.universe.set(x, y, flip(cell));
+    }
+
+    fn clear(&mut self) {
+        self.universe.clear();
+    }
+
+    /// Clears the universe, then fills the top-left cell of each square on screen randomly.
+    fn randomize(&mut self, percentage: u32, random: &mut Random) {
+        self.universe.clear();
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let cell = Cell::random_cell(percentage, random);
+                if cell.is_alive() {
+                    let (x, y) = self.to_universe(x, y);
+                    self.universe.set(x, y, cell);
+                }
+            }
+        }
+    }
+}
+
+impl Viewport {
+    /// The coordinates of the top-left corner of the viewport in the universe.
+    fn origin(&self) -> (i64, i64) {
+        Universe::centered_origin(self.width << self.zoom, self.height << self.zoom)
+    }
+
+    /// The coordinates in the universe of the top-left cell shown at `(x, y)`.
+    fn to_universe(&self, x: usize, y: usize) -> (i64, i64) {
+        let (left, top) = self.origin();
+        (
+            left + ((x as i64) << self.zoom),
+            top + ((y as i64) << self.zoom),
+        )
+    }
+}
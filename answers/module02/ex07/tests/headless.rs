@@ -0,0 +1,198 @@
+use std::process::Command;
+
+/// Runs the program and returns its standard output and error.
+fn run(args: &[&str]) -> (String, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_m02ex07"))
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    (
+        String::from_utf8(output.stdout).unwrap(),
+        String::from_utf8(output.stderr).unwrap(),
+    )
+}
+
+/// Runs the program with invalid arguments and returns its error message.
+fn fail(args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_m02ex07"))
+        .args(args)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+    String::from_utf8(output.stderr).unwrap()
+}
+
+#[test]
+fn reproducible() {
+    let args = [
+        "--seed",
+        "1234",
+        "--generations",
+        "50",
+        "--headless",
+        "32",
+        "24",
+        "35",
+    ];
+    let (first, summary) = run(&args);
+    let (second, _) = run(&args);
+    assert_eq!(first, second);
+    assert!(summary.ends_with("(seed 1234)\n"), "{summary}");
+
+    let lines: Vec<&str> = first.lines().collect();
+    assert_eq!(lines[0], "generation\tpopulation\tchange");
+    assert!(lines[1].starts_with("0\t"));
+
+    let (other, _) = run(&[
+        "--seed",
+        "4321",
+        "--generations",
+        "50",
+        "--headless",
+        "32",
+        "24",
+        "35",
+    ]);
+    assert_ne!(first, other);
+}
+
+#[test]
+fn statistics() {
+    let dir = std::env::temp_dir().join(format!("m02ex07-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("blinker.cells");
+    std::fs::write(&path, "OOO\n").unwrap();
+
+    let (stats, summary) = run(&["--load", path.to_str().unwrap(), "--headless", "5", "5"]);
+    assert_eq!(
+        stats,
+        "generation\tpopulation\tchange\n0\t3\t+0\n1\t3\t+0\n2\t3\t+0\n"
+    );
+    assert_eq!(
+        summary,
+        "the board has been repeating every 2 generations since generation 0\n"
+    );
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn final_board() {
+    let dir = std::env::temp_dir().join(format!("m02ex07-final-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("glider.rle");
+    std::fs::write(&path, "x = 3, y = 3\nbob$2bo$3o!\n").unwrap();
+    let path = path.to_str().unwrap();
+
+    // A glider is back to its shape every 4 generations.
+    for backend in [&[][..], &["--hashlife"][..]] {
+        let mut args = vec!["--load", path, "--generations", "8", "--dump", "cells"];
+        args.extend(backend);
+        args.extend(["20", "20"]);
+        let (pattern, summary) = run(&args);
+        assert_eq!(pattern, ".O\n..O\nOOO\n");
+        assert_eq!(summary, "stopped at generation 8\n");
+
+        args[3] = "9";
+        let (pattern, _) = run(&args);
+        assert_eq!(pattern, "O.O\n.OO\n.O\n");
+    }
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn b0_with_hashlife() {
+    let args = [
+        "--rule",
+        "B0/S8",
+        "--headless",
+        "--generations",
+        "3",
+        "5",
+        "5",
+        "50",
+    ];
+    assert_eq!(
+        fail(&[&["--hashlife"][..], &args].concat()),
+        "error: '--hashlife' cannot run B0/S8, where cells are born from nothing\n"
+    );
+    run(&args);
+}
+
+#[test]
+fn hashlife_limit() {
+    for option in ["--step", "--generations"] {
+        assert_eq!(
+            fail(&[
+                "--hashlife",
+                option,
+                "4611686018427387904",
+                "--headless",
+                "5",
+                "5",
+                "50"
+            ]),
+            "error: '--hashlife' cannot reach generation 4611686018427387904, the last one is \
+             288230376151711744\n"
+        );
+    }
+
+    let (stats, summary) = run(&[
+        "--hashlife",
+        "--step",
+        "288230376151711744",
+        "--seed",
+        "1",
+        "--headless",
+        "5",
+        "5",
+        "50",
+    ]);
+    // The last generation is reached in one step, and the run stops there.
+    let last = stats.lines().last().unwrap();
+    assert!(last.starts_with("288230376151711744\t"), "{stats}");
+    assert_eq!(stats.lines().count(), 3);
+    assert!(
+        summary.contains("generation 288230376151711744"),
+        "{summary}"
+    );
+}
+
+#[test]
+fn errors() {
+    assert_eq!(fail(&["5"]), "error: not enough arguments\n");
+    assert_eq!(
+        fail(&["--headless", "5", "five", "50"]),
+        "error: 'five' is not a valid height\n"
+    );
+    let error = fail(&["--load", "/nonexistent.rle", "5", "5"]);
+    assert!(
+        error.starts_with("error: cannot read '/nonexistent.rle': "),
+        "{error}"
+    );
+
+    let dir = std::env::temp_dir().join(format!("m02ex07-errors-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("big.rle");
+    std::fs::write(&path, "x = 10, y = 10\n18446744073709551615bo!\n").unwrap();
+    let path = path.to_str().unwrap();
+    assert_eq!(
+        fail(&["--load", path, "--dump", "rle", "10", "10"]),
+        format!("error: {path}: line 2: the pattern is larger than its header says\n")
+    );
+
+    let path = dir.join("glider.rle");
+    std::fs::write(&path, "x = 3, y = 3\nbob$2bo$3o!\n").unwrap();
+    let path = path.to_str().unwrap();
+    for at in ["18446744073709551615,0", "0,18446744073709551615", "8,0"] {
+        assert_eq!(
+            fail(&["--load", path, "--at", at, "--headless", "10", "10"]),
+            format!("error: '{path}' does not fit in the board at this position\n")
+        );
+    }
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
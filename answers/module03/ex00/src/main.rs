@@ -1,9 +1,32 @@
-fn choose<T>(values: &[T]) -> &T {
-    let index = ftkit::random_number(0..values.len() as i32) as usize;
-    &values[index]
-}
+mod random;
+
+use random::{choose, choose_weighted, sample, shuffle, Random};
 
+/// Usage: `m03ex00 [seed]`, the seed being picked from the time when not given.
 fn main() {
-    println!("{}", choose(&[1, 2, 3, 4]));
-    println!("{}", choose(&["abc", "def"]));
+    let seed = match ftkit::ARGS.into_iter().nth(1) {
+        Some(arg) => match arg.parse() {
+            Ok(seed) => seed,
+            Err(_) => {
+                eprintln!("error: '{arg}' is not a valid seed");
+                return;
+            }
+        },
+        None => Random::seed(),
+    };
+    let mut random = Random::new(seed);
+
+    println!("{:?}", choose(&mut random, &[1, 2, 3, 4]));
+    println!("{:?}", choose(&mut random, &["abc", "def"]));
+    println!("{:?}", choose::<i32>(&mut random, &[]));
+    println!(
+        "{:?}",
+        choose_weighted(&mut random, &["rare", "common"], &[1, 9])
+    );
+    println!("{:?}", sample(&mut random, &[1, 2, 3, 4, 5, 6], 3));
+
+    let mut deck = ['A', 'K', 'Q', 'J', 'T'];
+    shuffle(&mut random, &mut deck);
+    println!("{deck:?}");
+    println!("(seed {seed})");
 }
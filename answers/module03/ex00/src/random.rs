@@ -0,0 +1,197 @@
+//! Picking values at random, from a generator that can be seeded for reproducible results.
+
+/// The source of every random pick. Its whole state is a single number starting at the seed, so
+/// printing the seed is enough to replay a run.
+///
+/// This is SplitMix64, whose numbers are not suited to cryptography.
+pub struct Random {
+    state: u64,
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// Returns a seed that differs from one run to the next, taken from the clock.
+    pub fn seed() -> u64 {
+        std::time::SystemTime::UNIX_EPOCH
+            .elapsed()
+            .map_or(0, |elapsed| elapsed.as_nanos() as u64)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a number in `0..n`, which must not be empty.
+    ///
+    /// Numbers past the last multiple of `n` are drawn again, so that every result is equally
+    /// likely.
+    pub fn below(&mut self, n: u64) -> u64 {
+        assert!(n > 0, "cannot pick a number below 0");
+        let limit = u64::MAX - u64::MAX % n;
+        loop {
+            let x = self.next_u64();
+            if x < limit {
+                return x % n;
+            }
+        }
+    }
+}
+
+/// Picks one of `values`, or `None` if there are none.
+pub fn choose<'a, T>(random: &mut Random, values: &'a [T]) -> Option<&'a T> {
+    if values.is_empty() {
+        return None;
+    }
+    Some(&values[random.below(values.len() as u64) as usize])
+}
+
+/// Picks one of `values`, each being as likely as its weight. Returns `None` if no value has
+/// a weight.
+///
+/// # Panics
+///
+/// If there are not as many weights as values.
+pub fn choose_weighted<'a, T>(
+    random: &mut Random,
+    values: &'a [T],
+    weights: &[u32],
+) -> Option<&'a T> {
+    assert_eq!(
+        values.len(),
+        weights.len(),
+        "one weight per value is needed"
+    );
+    let total: u64 = weights.iter().map(|&weight| weight as u64).sum();
+    if total == 0 {
+        return None;
+    }
+
+    let mut target = random.below(total);
+    for (value, &weight) in values.iter().zip(weights) {
+        if target < weight as u64 {
+            return Some(value);
+        }
+        target -= weight as u64;
+    }
+    unreachable!("the target is below the total weight")
+}
+
+/// Shuffles `values` in place, every order being equally likely (Fisher–Yates).
+pub fn shuffle<T>(random: &mut Random, values: &mut [T]) {
+    for i in (1..values.len()).rev() {
+        let j = random.below(i as u64 + 1) as usize;
+        values.swap(i, j);
+    }
+}
+
+/// Picks `k` distinct values in random order, or all of them (shuffled) when there are fewer.
+pub fn sample<'a, T>(random: &mut Random, values: &'a [T], k: usize) -> Vec<&'a T> {
+    // A Fisher–Yates shuffle of the indices, stopped after the first `k`.
+    let mut indices: Vec<usize> = (0..values.len()).collect();
+    let k = k.min(values.len());
+    for i in 0..k {
+        let j = i + random.below((values.len() - i) as u64) as usize;
+        indices.swap(i, j);
+    }
+    indices[..k].iter().map(|&i| &values[i]).collect()
+}
+
+#[cfg(test)]
+#[test]
+fn seeded() {
+    let numbers = |seed| {
+        let mut random = Random::new(seed);
+        [random.next_u64(), random.next_u64()]
+    };
+    assert_eq!(numbers(1), numbers(1));
+    assert_ne!(numbers(1), numbers(2));
+
+    let mut random = Random::new(0);
+    for n in [1, 2, 3, 10, u64::MAX] {
+        assert!(random.below(n) < n);
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn choosing() {
+    let mut random = Random::new(42);
+    assert_eq!(choose::<i32>(&mut random, &[]), None);
+    assert_eq!(choose(&mut random, &[7]), Some(&7));
+
+    let mut counts = [0; 4];
+    for _ in 0..4000 {
+        counts[*choose(&mut random, &[0, 1, 2, 3]).unwrap()] += 1;
+    }
+    assert!(
+        counts.iter().all(|count| (900..1100).contains(count)),
+        "{counts:?}"
+    );
+}
+
+#[cfg(test)]
+#[test]
+fn choosing_weighted() {
+    let mut random = Random::new(42);
+    assert_eq!(choose_weighted::<i32>(&mut random, &[], &[]), None);
+    assert_eq!(choose_weighted(&mut random, &['a', 'b'], &[0, 0]), None);
+    assert_eq!(
+        choose_weighted(&mut random, &['a', 'b'], &[0, 1]),
+        Some(&'b')
+    );
+
+    let mut counts = [0; 3];
+    for _ in 0..6000 {
+        counts[*choose_weighted(&mut random, &[0, 1, 2], &[1, 0, 2]).unwrap()] += 1;
+    }
+    assert_eq!(counts[1], 0);
+    assert!((1800..2200).contains(&counts[0]), "{counts:?}");
+    assert!((3800..4200).contains(&counts[2]), "{counts:?}");
+}
+
+#[cfg(test)]
+#[test]
+#[should_panic = "one weight per value is needed"]
+fn missing_weights() {
+    choose_weighted(&mut Random::new(0), &[1, 2], &[1]);
+}
+
+#[cfg(test)]
+#[test]
+fn shuffling() {
+    let mut values: Vec<u32> = (0..50).collect();
+    shuffle(&mut Random::new(3), &mut values);
+    assert_ne!(values, (0..50).collect::<Vec<_>>());
+    let mut again: Vec<u32> = (0..50).collect();
+    shuffle(&mut Random::new(3), &mut again);
+    assert_eq!(values, again);
+    values.sort();
+    assert_eq!(values, (0..50).collect::<Vec<_>>());
+
+    shuffle::<u32>(&mut Random::new(3), &mut []);
+}
+
+#[cfg(test)]
+#[test]
+fn sampling() {
+    let mut random = Random::new(9);
+    let values: Vec<u32> = (0..20).collect();
+
+    let mut picked = sample(&mut random, &values, 5);
+    assert_eq!(picked.len(), 5);
+    picked.sort();
+    picked.dedup();
+    assert_eq!(picked.len(), 5);
+
+    let mut all = sample(&mut random, &values, 100);
+    all.sort();
+    assert_eq!(all, values.iter().collect::<Vec<_>>());
+    assert!(sample(&mut random, &values, 0).is_empty());
+}
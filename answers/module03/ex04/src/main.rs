@@ -1,68 +1,257 @@
+mod range;
+
 use std::fmt::Display;
+use std::ops::{Add, AddAssign, Sub, SubAssign};
 use std::str::FromStr;
+use std::time::Duration;
+
+use range::{free_slots, merge, TimeRange};
 
-#[derive(Debug)]
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// A time of day, from `00:00:00` to `23:59:59`. Times are ordered from midnight on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 struct Time {
     hours: u32,
     minutes: u32,
+    seconds: u32,
+}
+
+impl Time {
+    /// The number of seconds since midnight.
+    fn seconds_since_midnight(self) -> u64 {
+        (self.hours as u64 * 60 + self.minutes as u64) * 60 + self.seconds as u64
+    }
+
+    /// Returns the time `seconds` after midnight, wrapping around to the next days.
+    fn from_seconds_since_midnight(seconds: u64) -> Self {
+        let seconds = seconds % SECONDS_PER_DAY;
+        Self {
+            hours: (seconds / 3600) as u32,
+            minutes: (seconds / 60 % 60) as u32,
+            seconds: (seconds % 60) as u32,
+        }
+    }
 }
 
 impl Display for Time {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{} hours, {} minutes", self.hours, self.minutes)
+        write!(f, "{} hours, {} minutes", self.hours, self.minutes)?;
+        if self.seconds != 0 {
+            write!(f, ", {} seconds", self.seconds)?;
+        }
+        Ok(())
+    }
+}
+
+/// Adds a duration, wrapping around midnight. Fractions of a second are ignored.
+impl Add<Duration> for Time {
+    type Output = Self;
+
+    fn add(self, duration: Duration) -> Self {
+        let seconds = duration.as_secs() % SECONDS_PER_DAY;
+        Self::from_seconds_since_midnight(self.seconds_since_midnight() + seconds)
     }
 }
 
-#[derive(Debug)]
+/// Subtracts a duration, wrapping around midnight. Fractions of a second are ignored.
+impl Sub<Duration> for Time {
+    type Output = Self;
+
+    fn sub(self, duration: Duration) -> Self {
+        let seconds = duration.as_secs() % SECONDS_PER_DAY;
+        Self::from_seconds_since_midnight(self.seconds_since_midnight() + SECONDS_PER_DAY - seconds)
+    }
+}
+
+impl AddAssign<Duration> for Time {
+    fn add_assign(&mut self, duration: Duration) {
+        *self = *self + duration;
+    }
+}
+
+impl SubAssign<Duration> for Time {
+    fn sub_assign(&mut self, duration: Duration) {
+        *self = *self - duration;
+    }
+}
+
+/// Why a time could not be parsed. Positions count characters from 0.
+#[derive(Debug, PartialEq)]
 enum TimeParseError {
-    MissingColon,
-    InvalidLength,
-    InvalidNumber,
+    MissingColon {
+        position: usize,
+    },
+    InvalidNumber {
+        position: usize,
+    },
+    /// The number starting at `position` is too large, such as hour 24 or minute 60.
+    OutOfRange {
+        position: usize,
+    },
+    /// Something other than `am` or `pm` follows the time.
+    InvalidSuffix {
+        position: usize,
+    },
+    UnexpectedEnd {
+        position: usize,
+    },
 }
 
 impl Display for TimeParseError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Self::MissingColon => f.write_str("missing ':'"),
-            Self::InvalidLength => f.write_str("invalid length"),
-            Self::InvalidNumber => f.write_str("invalid number"),
+            Self::MissingColon { position } => write!(f, "missing ':' at position {position}"),
+            Self::InvalidNumber { position } => {
+                write!(f, "invalid number at position {position}")
+            }
+            Self::OutOfRange { position } => {
+                write!(f, "number out of range at position {position}")
+            }
+            Self::InvalidSuffix { position } => {
+                write!(f, "expected 'am' or 'pm' at position {position}")
+            }
+            Self::UnexpectedEnd { position } => {
+                write!(f, "unexpected end at position {position}")
+            }
         }
     }
 }
 
-impl FromStr for Time {
-    type Err = TimeParseError;
+impl TimeParseError {
+    /// Moves the position of the error `offset` characters further, for times that are part of
+    /// a longer string.
+    fn shifted(self, offset: usize) -> Self {
+        match self {
+            Self::MissingColon { position } => Self::MissingColon {
+                position: position + offset,
+            },
+            Self::InvalidNumber { position } => Self::InvalidNumber {
+                position: position + offset,
+            },
+            Self::OutOfRange { position } => Self::OutOfRange {
+                position: position + offset,
+            },
+            Self::InvalidSuffix { position } => Self::InvalidSuffix {
+                position: position + offset,
+            },
+            Self::UnexpectedEnd { position } => Self::UnexpectedEnd {
+                position: position + offset,
+            },
+        }
+    }
+}
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let bytes = s.as_bytes();
+/// Reads a time one character at a time, remembering where it is for errors.
+struct Parser {
+    chars: Vec<char>,
+    position: usize,
+}
 
-        if bytes.len() != 5 {
-            return Err(TimeParseError::InvalidLength);
-        }
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
 
-        if bytes[2] != b':' {
-            return Err(TimeParseError::MissingColon);
+    fn colon(&mut self) -> Result<(), TimeParseError> {
+        match self.peek() {
+            Some(':') => {
+                self.position += 1;
+                Ok(())
+            }
+            Some(_) => Err(TimeParseError::MissingColon {
+                position: self.position,
+            }),
+            None => Err(TimeParseError::UnexpectedEnd {
+                position: self.position,
+            }),
         }
+    }
 
-        if !bytes[0].is_ascii_digit() || !bytes[1].is_ascii_digit() {
-            return Err(TimeParseError::InvalidNumber);
+    /// Parses a number of `min_digits` to `max_digits` digits, which must be below `limit`.
+    fn number(
+        &mut self,
+        min_digits: usize,
+        max_digits: usize,
+        limit: u32,
+    ) -> Result<u32, TimeParseError> {
+        let start = self.position;
+        let mut number = 0;
+        while self.position - start < max_digits {
+            match self.peek().and_then(|c| c.to_digit(10)) {
+                Some(digit) => number = number * 10 + digit,
+                None if self.position - start >= min_digits => break,
+                None if self.peek().is_none() => {
+                    return Err(TimeParseError::UnexpectedEnd {
+                        position: self.position,
+                    })
+                }
+                None => {
+                    return Err(TimeParseError::InvalidNumber {
+                        position: self.position,
+                    })
+                }
+            }
+            self.position += 1;
         }
-
-        let hours = ((bytes[0] - b'0') * 10 + bytes[1] - b'0') as u32;
-        if hours >= 60 {
-            return Err(TimeParseError::InvalidNumber);
+        if number >= limit {
+            return Err(TimeParseError::OutOfRange { position: start });
         }
+        Ok(number)
+    }
+}
 
-        if !bytes[3].is_ascii_digit() || !bytes[4].is_ascii_digit() {
-            return Err(TimeParseError::InvalidNumber);
+impl FromStr for Time {
+    type Err = TimeParseError;
+
+    /// Parses `HH:MM` or `HH:MM:SS`, where hours may have a single digit, optionally followed
+    /// by `am` or `pm` to count hours from 1 to 12.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            chars: s.chars().collect(),
+            position: 0,
+        };
+
+        let hours = parser.number(1, 2, 24)?;
+        parser.colon()?;
+        let minutes = parser.number(2, 2, 60)?;
+        let seconds = if parser.peek() == Some(':') {
+            parser.colon()?;
+            parser.number(2, 2, 60)?
+        } else {
+            0
+        };
+
+        while parser.peek() == Some(' ') {
+            parser.position += 1;
+        }
+        if parser.peek().is_none() {
+            return Ok(Self {
+                hours,
+                minutes,
+                seconds,
+            });
         }
 
-        let minutes = ((bytes[3] - b'0') * 10 + bytes[4] - b'0') as u32;
-        if minutes >= 60 {
-            return Err(TimeParseError::InvalidNumber);
+        let suffix: String = parser.chars[parser.position..].iter().collect();
+        let afternoon = match suffix.to_ascii_lowercase().as_str() {
+            "am" => false,
+            "pm" => true,
+            _ => {
+                return Err(TimeParseError::InvalidSuffix {
+                    position: parser.position,
+                })
+            }
+        };
+        if !(1..=12).contains(&hours) {
+            return Err(TimeParseError::OutOfRange { position: 0 });
         }
 
-        Ok(Self { hours, minutes })
+        Ok(Self {
+            hours: hours % 12 + if afternoon { 12 } else { 0 },
+            minutes,
+            seconds,
+        })
     }
 }
 
@@ -79,4 +268,132 @@ fn main() {
     println!("error: {err1}");
     println!("error: {err2}");
     println!("error: {err3}");
+
+    let c: Time = "9:05:30 pm".parse().unwrap();
+    println!("{c}");
+    println!("{}", c + Duration::from_secs(3 * 60 * 60));
+    println!("{}", a.min(b));
+
+    let bookings: Vec<TimeRange> = ["14:00-15:00", "09:00-10:30", "10:00-11:00", "16:30-17:00"]
+        .iter()
+        .map(|s| s.parse().unwrap())
+        .collect();
+    let day: TimeRange = "08:00-18:00".parse().unwrap();
+    let (a, b) = (bookings[1], bookings[2]);
+    println!("{a} overlaps {b}: {}", a.overlaps(b));
+    println!("{day} contains {a}: {}", day.contains(a));
+    println!(
+        "{a} contains 10:30: {}",
+        a.contains_time("10:30".parse().unwrap())
+    );
+    println!("{a} lasts {} minutes", a.duration().as_secs() / 60);
+    for range in merge(&bookings) {
+        println!("busy: {range}");
+    }
+    for range in free_slots(&bookings, day) {
+        println!("free: {range}");
+    }
+    println!("error: {}", "10:00-9:00".parse::<TimeRange>().unwrap_err());
+}
+
+#[cfg(test)]
+fn time(hours: u32, minutes: u32, seconds: u32) -> Time {
+    Time {
+        hours,
+        minutes,
+        seconds,
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn parsing() {
+    assert_eq!("00:00".parse(), Ok(time(0, 0, 0)));
+    assert_eq!("23:59:59".parse(), Ok(time(23, 59, 59)));
+    assert_eq!("7:05".parse(), Ok(time(7, 5, 0)));
+    assert_eq!("12:30 am".parse(), Ok(time(0, 30, 0)));
+    assert_eq!("12:30pm".parse(), Ok(time(12, 30, 0)));
+    assert_eq!("1:15:20 PM".parse(), Ok(time(13, 15, 20)));
+    assert_eq!("11:59 Am".parse(), Ok(time(11, 59, 0)));
+}
+
+#[cfg(test)]
+#[test]
+fn errors() {
+    let error = |s: &str| s.parse::<Time>().unwrap_err();
+    assert_eq!(error("12.20"), TimeParseError::MissingColon { position: 2 });
+    assert_eq!(
+        error("123:00"),
+        TimeParseError::MissingColon { position: 2 }
+    );
+    assert_eq!(error("12:2"), TimeParseError::UnexpectedEnd { position: 4 });
+    assert_eq!(
+        error("12:2a"),
+        TimeParseError::InvalidNumber { position: 4 }
+    );
+    assert_eq!(error(":30"), TimeParseError::InvalidNumber { position: 0 });
+    assert_eq!(error(""), TimeParseError::UnexpectedEnd { position: 0 });
+    assert_eq!(error("24:00"), TimeParseError::OutOfRange { position: 0 });
+    assert_eq!(error("10:60"), TimeParseError::OutOfRange { position: 3 });
+    assert_eq!(
+        error("10:00:60"),
+        TimeParseError::OutOfRange { position: 6 }
+    );
+    assert_eq!(
+        error("13:00 pm"),
+        TimeParseError::OutOfRange { position: 0 }
+    );
+    assert_eq!(error("0:00 am"), TimeParseError::OutOfRange { position: 0 });
+    assert_eq!(
+        error("10:00 h"),
+        TimeParseError::InvalidSuffix { position: 6 }
+    );
+    assert_eq!(
+        error("10:00:"),
+        TimeParseError::UnexpectedEnd { position: 6 }
+    );
+    assert_eq!(
+        error("é10:00"),
+        TimeParseError::InvalidNumber { position: 0 }
+    );
+    assert_eq!(error("1é:00"), TimeParseError::MissingColon { position: 1 });
+}
+
+#[cfg(test)]
+#[test]
+fn ordering() {
+    let mut times = [
+        time(13, 0, 0),
+        time(9, 30, 0),
+        time(9, 5, 59),
+        time(9, 30, 1),
+    ];
+    times.sort();
+    assert_eq!(
+        times,
+        [
+            time(9, 5, 59),
+            time(9, 30, 0),
+            time(9, 30, 1),
+            time(13, 0, 0)
+        ]
+    );
+}
+
+#[cfg(test)]
+#[test]
+fn arithmetic() {
+    let minutes = |n: u64| Duration::from_secs(n * 60);
+    assert_eq!(time(9, 30, 0) + minutes(45), time(10, 15, 0));
+    assert_eq!(time(23, 30, 0) + minutes(45), time(0, 15, 0));
+    assert_eq!(time(0, 15, 0) - minutes(30), time(23, 45, 0));
+    assert_eq!(time(12, 0, 0) + minutes(3 * 24 * 60), time(12, 0, 0));
+    assert_eq!(time(12, 0, 0) - minutes(3 * 24 * 60 + 1), time(11, 59, 0));
+    assert_eq!(time(0, 0, 0) + Duration::from_millis(1999), time(0, 0, 1));
+
+    let mut t = time(22, 0, 0);
+    t += minutes(150);
+    assert_eq!(t, time(0, 30, 0));
+    t -= Duration::from_secs(31);
+    assert_eq!(t, time(0, 29, 29));
 }
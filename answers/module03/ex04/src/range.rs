@@ -0,0 +1,297 @@
+//! Ranges of time within a day, such as room bookings.
+
+use std::fmt::Display;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::{Time, TimeParseError, SECONDS_PER_DAY};
+
+/// The times from `start` up to, but not including, `end`, which comes later the same day.
+///
+/// A range can end at midnight, at the end of the day, which is written `24:00`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TimeRange {
+    /// Seconds since midnight, up to [`SECONDS_PER_DAY`] for the end.
+    start: u64,
+    end: u64,
+}
+
+impl TimeRange {
+    /// Returns `None` unless `start` comes before `end`. An `end` of `00:00` is the midnight
+    /// at the end of the day, except when `start` is `00:00` as well: like any range that ends
+    /// when it starts, `00:00-00:00` is empty.
+    pub fn new(start: Time, end: Time) -> Option<Self> {
+        let start = start.seconds_since_midnight();
+        let end = match end.seconds_since_midnight() {
+            0 if start != 0 => SECONDS_PER_DAY,
+            end => end,
+        };
+        Self::from_seconds(start, end)
+    }
+
+    fn from_seconds(start: u64, end: u64) -> Option<Self> {
+        (start < end).then_some(Self { start, end })
+    }
+
+    pub fn duration(self) -> Duration {
+        Duration::from_secs(self.end - self.start)
+    }
+
+    pub fn contains_time(self, time: Time) -> bool {
+        let time = time.seconds_since_midnight();
+        self.start <= time && time < self.end
+    }
+
+    /// Whether every time of `other` is in this range.
+    pub fn contains(self, other: Self) -> bool {
+        self.start <= other.start && other.end <= self.end
+    }
+
+    /// Whether some time is in both ranges. Ranges that only touch, such as `09:00-10:00` and
+    /// `10:00-11:00`, do not overlap.
+    pub fn overlaps(self, other: Self) -> bool {
+        self.start < other.end && other.start < self.end
+    }
+}
+
+/// Writes a time given in seconds since midnight as `HH:MM`, or `HH:MM:SS` when it has
+/// seconds. The end of the day is `24:00`.
+fn write_time(f: &mut std::fmt::Formatter<'_>, seconds: u64) -> std::fmt::Result {
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    write!(f, "{hours:02}:{minutes:02}")?;
+    if seconds != 0 {
+        write!(f, ":{seconds:02}")?;
+    }
+    Ok(())
+}
+
+impl Display for TimeRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write_time(f, self.start)?;
+        f.write_str("-")?;
+        write_time(f, self.end)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum TimeRangeParseError {
+    MissingDash,
+    /// One of the times is invalid. Positions are from the start of the range.
+    InvalidTime(TimeParseError),
+    /// The end does not come after the start.
+    Empty,
+}
+
+impl Display for TimeRangeParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MissingDash => f.write_str("missing '-'"),
+            Self::InvalidTime(error) => error.fmt(f),
+            Self::Empty => f.write_str("the range ends before it starts"),
+        }
+    }
+}
+
+impl FromStr for TimeRange {
+    type Err = TimeRangeParseError;
+
+    /// Parses two times separated by a dash, such as `09:00-10:30`. The end can be `24:00`, or
+    /// `00:00` after another start, for midnight at the end of the day.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (start, end) = s.split_once('-').ok_or(TimeRangeParseError::MissingDash)?;
+        let start = start.trim_end();
+        let end = end.trim_start();
+        // Where the end starts in `s`, in characters.
+        let end_offset = s[..s.len() - end.len()].chars().count();
+
+        let start: Time = start.parse().map_err(TimeRangeParseError::InvalidTime)?;
+        let range = match end {
+            "24:00" | "24:00:00" => {
+                Self::from_seconds(start.seconds_since_midnight(), SECONDS_PER_DAY)
+            }
+            end => Self::new(
+                start,
+                end.parse().map_err(|error: TimeParseError| {
+                    TimeRangeParseError::InvalidTime(error.shifted(end_offset))
+                })?,
+            ),
+        };
+        range.ok_or(TimeRangeParseError::Empty)
+    }
+}
+
+/// Sorts `ranges` and joins those that overlap or touch, so that none of the resulting ranges
+/// do.
+pub fn merge(ranges: &[TimeRange]) -> Vec<TimeRange> {
+    let mut ranges = ranges.to_vec();
+    ranges.sort();
+
+    let mut merged: Vec<TimeRange> = Vec::new();
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+            _ => merged.push(range),
+        }
+    }
+    merged
+}
+
+/// Returns the parts of `day` that are in none of the `busy` ranges, in order.
+pub fn free_slots(busy: &[TimeRange], day: TimeRange) -> Vec<TimeRange> {
+    let mut free = Vec::new();
+    let mut start = day.start;
+    for range in merge(busy) {
+        if range.end <= start {
+            continue;
+        }
+        if range.start >= day.end {
+            break;
+        }
+        free.extend(TimeRange::from_seconds(start, range.start));
+        start = range.end;
+    }
+    free.extend(TimeRange::from_seconds(start, day.end));
+    free
+}
+
+#[cfg(test)]
+fn range(s: &str) -> TimeRange {
+    s.parse().unwrap()
+}
+
+#[cfg(test)]
+#[test]
+fn parsing() {
+    assert_eq!(range("09:00-10:30").to_string(), "09:00-10:30");
+    assert_eq!(range("9:00 am - 1:15:30 pm").to_string(), "09:00-13:15:30");
+    assert_eq!(
+        range("09:00-10:30").duration(),
+        Duration::from_secs(90 * 60)
+    );
+
+    let error = |s: &str| s.parse::<TimeRange>().unwrap_err();
+    assert_eq!(error("09:00"), TimeRangeParseError::MissingDash);
+    assert_eq!(error("10:00-09:00"), TimeRangeParseError::Empty);
+    assert_eq!(error("10:00-10:00"), TimeRangeParseError::Empty);
+    assert_eq!(
+        error("1O:00-11:00"),
+        TimeRangeParseError::InvalidTime(TimeParseError::MissingColon { position: 1 })
+    );
+    assert_eq!(
+        error("10:00 - 11:6O"),
+        TimeRangeParseError::InvalidTime(TimeParseError::InvalidNumber { position: 12 })
+    );
+    assert_eq!(
+        error("10:00-25:00").to_string(),
+        "number out of range at position 6"
+    );
+
+    // Ranges can end at midnight, but not start at the end of the day.
+    assert_eq!(range("23:00-24:00").to_string(), "23:00-24:00");
+    assert_eq!(range("23:00-00:00"), range("23:00-24:00"));
+    assert_eq!(range("11:30 pm - 12:00 am").to_string(), "23:30-24:00");
+    assert_eq!(range("00:00-24:00").duration(), Duration::from_secs(86400));
+    // Like other ranges that end when they start, `00:00-00:00` is empty.
+    assert_eq!(error("00:00-00:00"), TimeRangeParseError::Empty);
+    assert_eq!(error("12:00 am - 12:00 am"), TimeRangeParseError::Empty);
+    let midnight = Time::from_seconds_since_midnight(0);
+    assert_eq!(TimeRange::new(midnight, midnight), None);
+    assert_eq!(
+        error("24:00-24:00"),
+        TimeRangeParseError::InvalidTime(TimeParseError::OutOfRange { position: 0 })
+    );
+    assert_eq!(
+        error("23:00-24:30"),
+        TimeRangeParseError::InvalidTime(TimeParseError::OutOfRange { position: 6 })
+    );
+}
+
+#[cfg(test)]
+#[test]
+fn overlap_and_containment() {
+    let morning = range("09:00-12:00");
+    assert!(morning.overlaps(range("11:00-13:00")));
+    assert!(morning.overlaps(range("10:00-11:00")));
+    assert!(!morning.overlaps(range("12:00-13:00")));
+    assert!(!range("08:00-09:00").overlaps(morning));
+
+    assert!(morning.contains(range("10:00-11:00")));
+    assert!(morning.contains(morning));
+    assert!(!morning.contains(range("11:00-13:00")));
+    assert!(morning.contains_time("09:00".parse().unwrap()));
+    assert!(!morning.contains_time("12:00".parse().unwrap()));
+
+    let night = range("22:00-24:00");
+    assert!(night.contains_time("23:59:59".parse().unwrap()));
+    assert!(!night.contains_time("00:00".parse().unwrap()));
+    assert!(night.contains(range("23:00-00:00")));
+    assert!(!night.overlaps(range("00:00-01:00")));
+}
+
+#[cfg(test)]
+#[test]
+fn merging() {
+    let ranges = [
+        range("14:00-15:00"),
+        range("09:00-10:00"),
+        range("09:30-11:00"),
+        range("11:00-11:30"),
+        range("13:00-13:30"),
+        range("14:15-14:45"),
+    ];
+    assert_eq!(
+        merge(&ranges),
+        [
+            range("09:00-11:30"),
+            range("13:00-13:30"),
+            range("14:00-15:00")
+        ]
+    );
+    assert_eq!(merge(&[]), []);
+}
+
+#[cfg(test)]
+#[test]
+fn free() {
+    let busy = [
+        range("07:00-08:30"),
+        range("10:00-11:00"),
+        range("10:30-12:00"),
+        range("13:00-14:00"),
+        range("17:30-20:00"),
+    ];
+    assert_eq!(
+        free_slots(&busy, range("08:00-18:00")),
+        [
+            range("08:30-10:00"),
+            range("12:00-13:00"),
+            range("14:00-17:30")
+        ]
+    );
+    assert_eq!(
+        free_slots(&[], range("08:00-18:00")),
+        [range("08:00-18:00")]
+    );
+    assert_eq!(
+        free_slots(&[range("00:00-23:00")], range("08:00-18:00")),
+        []
+    );
+
+    // The last slot of the day ends at midnight.
+    let whole_day = range("00:00-24:00");
+    assert_eq!(
+        free_slots(&busy, whole_day),
+        [
+            range("00:00-07:00"),
+            range("08:30-10:00"),
+            range("12:00-13:00"),
+            range("14:00-17:30"),
+            range("20:00-24:00")
+        ]
+    );
+    assert_eq!(
+        free_slots(&[range("22:00-24:00")], whole_day),
+        [range("00:00-22:00")]
+    );
+    assert_eq!(free_slots(&[whole_day], whole_day), []);
+}
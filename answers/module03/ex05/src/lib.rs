@@ -1,116 +1,200 @@
+mod matrix;
+mod scalar;
+
 use std::ops::*;
 
-#[derive(Debug, Clone, Copy)]
-pub struct Vector<T> {
-    x: T,
-    y: T,
+pub use matrix::Matrix;
+pub use scalar::{Float, Scalar};
+
+/// A vector of `N` components, two by default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Vector<T, const N: usize = 2> {
+    components: [T; N],
 }
 
 impl<T> Vector<T> {
     pub fn new(x: T, y: T) -> Self {
-        Self { x, y }
+        Self { components: [x, y] }
+    }
+}
+
+impl<T, const N: usize> Vector<T, N> {
+    pub fn from_array(components: [T; N]) -> Self {
+        Self { components }
+    }
+
+    pub fn into_array(self) -> [T; N] {
+        self.components
+    }
+
+    /// Applies `f` to each component.
+    pub fn map<U>(self, f: impl FnMut(T) -> U) -> Vector<U, N> {
+        Vector::from_array(self.components.map(f))
+    }
+
+    /// Applies `f` to each component and the matching one of `other`.
+    pub fn zip_map<U, V>(self, other: Vector<U, N>, mut f: impl FnMut(T, U) -> V) -> Vector<V, N> {
+        let mut other = other.components.into_iter();
+        self.map(|a| f(a, other.next().unwrap()))
     }
 }
 
-impl Vector<f32> {
-    pub fn length(&self) -> f32 {
-        (self.x * self.x + self.y * self.y).sqrt()
+impl<T, const N: usize> From<[T; N]> for Vector<T, N> {
+    fn from(components: [T; N]) -> Self {
+        Self::from_array(components)
     }
 }
 
-impl Vector<f64> {
-    pub fn length(&self) -> f64 {
-        (self.x * self.x + self.y * self.y).sqrt()
+impl<T, const N: usize> Index<usize> for Vector<T, N> {
+    type Output = T;
+
+    fn index(&self, index: usize) -> &T {
+        &self.components[index]
     }
 }
 
-impl<T: Add<Output = T>> Add for Vector<T> {
+impl<T, const N: usize> IndexMut<usize> for Vector<T, N> {
+    fn index_mut(&mut self, index: usize) -> &mut T {
+        &mut self.components[index]
+    }
+}
+
+impl<T: Scalar, const N: usize> Vector<T, N> {
+    pub fn zero() -> Self {
+        Self::from_array([T::ZERO; N])
+    }
+
+    pub fn dot(self, rhs: Self) -> T {
+        self.zip_map(rhs, |a, b| a * b)
+            .components
+            .into_iter()
+            .fold(T::ZERO, |sum, x| sum + x)
+    }
+
+    /// Multiplies each component by the matching one of `rhs`.
+    pub fn mul_components(self, rhs: Self) -> Self {
+        self.zip_map(rhs, |a, b| a * b)
+    }
+
+    /// Divides each component by the matching one of `rhs`.
+    pub fn div_components(self, rhs: Self) -> Self {
+        self.zip_map(rhs, |a, b| a / b)
+    }
+}
+
+impl<T: Scalar> Vector<T, 2> {
+    /// The `z` component of the cross product of the two vectors, extended in 3D.
+    pub fn cross(self, rhs: Self) -> T {
+        self[0] * rhs[1] - self[1] * rhs[0]
+    }
+}
+
+impl<T: Scalar> Vector<T, 3> {
+    pub fn cross(self, rhs: Self) -> Self {
+        let [a, b, c] = self.components;
+        let [x, y, z] = rhs.components;
+        Self::from_array([b * z - c * y, c * x - a * z, a * y - b * x])
+    }
+}
+
+impl<T: Float, const N: usize> Vector<T, N> {
+    pub fn length(&self) -> T {
+        self.dot(*self).sqrt()
+    }
+
+    /// Returns the vector with the same direction and a length of 1, unless it is zero.
+    pub fn normalized(self) -> Option<Self> {
+        // Scaling the vector first keeps the squares of its components from underflowing or
+        // overflowing.
+        let largest = self
+            .components
+            .into_iter()
+            .map(T::abs)
+            .fold(T::ZERO, |a, b| if b > a { b } else { a });
+        if largest == T::ZERO {
+            return None;
+        }
+        let scaled = self / largest;
+        Some(scaled / scaled.length())
+    }
+}
+
+impl<T: Add<Output = T>, const N: usize> Add for Vector<T, N> {
     type Output = Self;
 
     fn add(self, rhs: Self) -> Self::Output {
-        Self {
-            x: self.x + rhs.x,
-            y: self.y + rhs.y,
-        }
+        self.zip_map(rhs, |a, b| a + b)
     }
 }
 
-impl<T: Sub<Output = T>> Sub for Vector<T> {
+impl<T: Sub<Output = T>, const N: usize> Sub for Vector<T, N> {
     type Output = Self;
 
     fn sub(self, rhs: Self) -> Self::Output {
-        Self {
-            x: self.x - rhs.x,
-            y: self.y - rhs.y,
-        }
+        self.zip_map(rhs, |a, b| a - b)
     }
 }
 
-impl<T: AddAssign> AddAssign for Vector<T> {
+impl<T: Neg<Output = T>, const N: usize> Neg for Vector<T, N> {
+    type Output = Self;
+
+    fn neg(self) -> Self::Output {
+        self.map(|a| -a)
+    }
+}
+
+impl<T: AddAssign, const N: usize> AddAssign for Vector<T, N> {
     fn add_assign(&mut self, rhs: Self) {
-        self.x += rhs.x;
-        self.y += rhs.y;
+        for (a, b) in self.components.iter_mut().zip(rhs.components) {
+            *a += b;
+        }
     }
 }
 
-impl<T: SubAssign> SubAssign for Vector<T> {
+impl<T: SubAssign, const N: usize> SubAssign for Vector<T, N> {
     fn sub_assign(&mut self, rhs: Self) {
-        self.x -= rhs.x;
-        self.y -= rhs.y;
+        for (a, b) in self.components.iter_mut().zip(rhs.components) {
+            *a -= b;
+        }
     }
 }
 
-impl<T: Copy + Mul<Output = T>> Mul<T> for Vector<T> {
+impl<T: Copy + Mul<Output = T>, const N: usize> Mul<T> for Vector<T, N> {
     type Output = Self;
 
     fn mul(self, rhs: T) -> Self::Output {
-        Self {
-            x: self.x * rhs,
-            y: self.y * rhs,
-        }
+        self.map(|a| a * rhs)
     }
 }
 
-impl<T: Copy + Div<Output = T>> Div<T> for Vector<T> {
+impl<T: Copy + Div<Output = T>, const N: usize> Div<T> for Vector<T, N> {
     type Output = Self;
 
     fn div(self, rhs: T) -> Self::Output {
-        Self {
-            x: self.x / rhs,
-            y: self.y / rhs,
-        }
+        self.map(|a| a / rhs)
     }
 }
 
-impl<T: Copy + MulAssign> MulAssign<T> for Vector<T> {
+impl<T: Copy + MulAssign, const N: usize> MulAssign<T> for Vector<T, N> {
     fn mul_assign(&mut self, rhs: T) {
-        self.x *= rhs;
-        self.y *= rhs;
+        for a in &mut self.components {
+            *a *= rhs;
+        }
     }
 }
 
-impl<T: Copy + DivAssign> DivAssign<T> for Vector<T> {
+impl<T: Copy + DivAssign, const N: usize> DivAssign<T> for Vector<T, N> {
     fn div_assign(&mut self, rhs: T) {
-        self.x /= rhs;
-        self.y /= rhs;
-    }
-}
-
-impl<T: PartialEq> PartialEq for Vector<T> {
-    fn eq(&self, other: &Self) -> bool {
-        self.x == other.x && self.y == other.y
+        for a in &mut self.components {
+            *a /= rhs;
+        }
     }
 }
 
-impl<T: Eq> Eq for Vector<T> {}
-
 #[cfg(test)]
 #[test]
 fn test_a() {
-    let v = Vector {
-        x: String::from("Hello, World!"),
-        y: String::from("Hello, Rust!"),
-    };
+    let v = Vector::new(String::from("Hello, World!"), String::from("Hello, Rust!"));
 
     let w = v.clone();
 
@@ -125,3 +209,70 @@ fn test_b() {
     let b = v;
     assert_eq!(a, b);
 }
+
+#[cfg(test)]
+#[test]
+fn arithmetic() {
+    let a = Vector::from([1, 2, 3]);
+    let b = Vector::from([4, 5, 6]);
+    assert_eq!(a + b, Vector::from([5, 7, 9]));
+    assert_eq!(b - a, Vector::from([3, 3, 3]));
+    assert_eq!(-a, Vector::from([-1, -2, -3]));
+    assert_eq!(a * 2, Vector::from([2, 4, 6]));
+    assert_eq!(b / 2, Vector::from([2, 2, 3]));
+    assert_eq!(a.mul_components(b), Vector::from([4, 10, 18]));
+    assert_eq!(b.div_components(a), Vector::from([4, 2, 2]));
+
+    let mut c = a;
+    c += b;
+    c -= Vector::from([1, 1, 1]);
+    c *= 3;
+    c /= 2;
+    assert_eq!(c, Vector::from([6, 9, 12]));
+    assert_eq!(Vector::<i32, 4>::zero().into_array(), [0; 4]);
+}
+
+#[cfg(test)]
+#[test]
+fn products() {
+    let a = Vector::from([1, 2, 3]);
+    let b = Vector::from([4, 5, 6]);
+    assert_eq!(a.dot(b), 32);
+    assert_eq!(a.cross(b), Vector::from([-3, 6, -3]));
+    assert_eq!(b.cross(a), -a.cross(b));
+    assert_eq!(a.cross(b).dot(a), 0);
+
+    let x = Vector::from([1, 0, 0]);
+    let y = Vector::from([0, 1, 0]);
+    assert_eq!(x.cross(y), Vector::from([0, 0, 1]));
+
+    assert_eq!(Vector::new(1, 0).cross(Vector::new(0, 1)), 1);
+    assert_eq!(Vector::new(2, 3).cross(Vector::new(4, 6)), 0);
+}
+
+#[cfg(test)]
+#[test]
+fn lengths() {
+    assert_eq!(Vector::new(3.0f32, 4.0).length(), 5.0);
+    assert_eq!(Vector::from([2.0f64, 3.0, 6.0]).length(), 7.0);
+    assert_eq!(
+        Vector::new(3.0f64, 4.0).normalized(),
+        Some(Vector::new(0.6, 0.8))
+    );
+    assert_eq!(Vector::<f64, 3>::zero().normalized(), None);
+    assert_eq!(
+        Vector::new(3e-20f32, 4e-20).normalized(),
+        Some(Vector::new(0.6, 0.8))
+    );
+    assert_eq!(
+        Vector::new(0.0, -1e-300f64).normalized(),
+        Some(Vector::new(0.0, -1.0))
+    );
+    assert_eq!(
+        Vector::new(3e300f64, 4e300).normalized(),
+        Some(Vector::new(0.6, 0.8))
+    );
+
+    let v = Vector::from([1.0f64, -2.0, 2.0, 4.0]).normalized().unwrap();
+    assert!((v.length() - 1.0).abs() < 1e-12);
+}
@@ -0,0 +1,288 @@
+//! Matrices of `R` rows and `C` columns.
+
+use std::ops::*;
+
+use crate::{Float, Scalar, Vector};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Matrix<T, const R: usize, const C: usize> {
+    rows: [[T; C]; R],
+}
+
+impl<T, const R: usize, const C: usize> Matrix<T, R, C> {
+    pub fn new(rows: [[T; C]; R]) -> Self {
+        Self { rows }
+    }
+
+    pub fn into_rows(self) -> [[T; C]; R] {
+        self.rows
+    }
+}
+
+impl<T: Copy, const R: usize, const C: usize> Matrix<T, R, C> {
+    pub fn row(&self, row: usize) -> Vector<T, C> {
+        Vector::from_array(self.rows[row])
+    }
+
+    pub fn column(&self, column: usize) -> Vector<T, R> {
+        Vector::from_array(self.rows.map(|row| row[column]))
+    }
+
+    pub fn transpose(&self) -> Matrix<T, C, R> {
+        Matrix::new(std::array::from_fn(|column| {
+            self.column(column).into_array()
+        }))
+    }
+}
+
+impl<T: Scalar, const N: usize> Matrix<T, N, N> {
+    pub fn identity() -> Self {
+        Self::new(std::array::from_fn(|i| {
+            std::array::from_fn(|j| if i == j { T::ONE } else { T::ZERO })
+        }))
+    }
+}
+
+/// Returns the row at or below `column` with the largest value in that column, to limit rounding
+/// errors when dividing by it.
+fn pivot<T: Float, const N: usize>(rows: &[[T; N]; N], column: usize) -> usize {
+    (column..N)
+        .reduce(|a, b| {
+            if rows[b][column].abs() > rows[a][column].abs() {
+                b
+            } else {
+                a
+            }
+        })
+        .unwrap()
+}
+
+/// Returns the largest absolute value of the matrix, which pivots are compared to so that
+/// scaling a matrix does not make it singular.
+fn largest<T: Float, const N: usize>(rows: &[[T; N]; N]) -> T {
+    rows.iter()
+        .flatten()
+        .map(|a| a.abs())
+        .fold(T::ZERO, |a, b| if b > a { b } else { a })
+}
+
+/// Subtracts row `source` times `factor` from row `target`.
+fn subtract_scaled<T: Scalar, const N: usize>(
+    rows: &mut [[T; N]; N],
+    target: usize,
+    source: usize,
+    factor: T,
+) {
+    let source = rows[source];
+    for (a, b) in rows[target].iter_mut().zip(source) {
+        *a = *a - factor * b;
+    }
+}
+
+impl<T: Float, const N: usize> Matrix<T, N, N> {
+    /// Computes the determinant by Gaussian elimination.
+    ///
+    /// It is zero when a pivot is negligible next to the largest value of the matrix.
+    pub fn determinant(&self) -> T {
+        let mut rows = self.rows;
+        let tolerance = largest(&rows) * T::EPSILON;
+        let mut determinant = T::ONE;
+        for column in 0..N {
+            let pivot = pivot(&rows, column);
+            if rows[pivot][column].abs() <= tolerance {
+                return T::ZERO;
+            }
+            if pivot != column {
+                rows.swap(pivot, column);
+                determinant = -determinant;
+            }
+            determinant = determinant * rows[column][column];
+
+            for row in column + 1..N {
+                let factor = rows[row][column] / rows[column][column];
+                subtract_scaled(&mut rows, row, column, factor);
+            }
+        }
+        determinant
+    }
+
+    /// Computes the inverse by Gauss-Jordan elimination, or returns `None` if the matrix is
+    /// singular, as for [`Matrix::determinant`].
+    pub fn inverse(&self) -> Option<Self> {
+        let mut rows = self.rows;
+        let tolerance = largest(&rows) * T::EPSILON;
+        let mut inverse = Self::identity().rows;
+        for column in 0..N {
+            let pivot = pivot(&rows, column);
+            if rows[pivot][column].abs() <= tolerance {
+                return None;
+            }
+            rows.swap(pivot, column);
+            inverse.swap(pivot, column);
+
+            let scale = rows[column][column];
+            rows[column] = rows[column].map(|a| a / scale);
+            inverse[column] = inverse[column].map(|a| a / scale);
+            for row in 0..N {
+                if row != column {
+                    let factor = rows[row][column];
+                    subtract_scaled(&mut rows, row, column, factor);
+                    subtract_scaled(&mut inverse, row, column, factor);
+                }
+            }
+        }
+        Some(Self::new(inverse))
+    }
+}
+
+impl<T, const R: usize, const C: usize> Index<(usize, usize)> for Matrix<T, R, C> {
+    type Output = T;
+
+    /// Returns the value at `(row, column)`.
+    fn index(&self, (row, column): (usize, usize)) -> &T {
+        &self.rows[row][column]
+    }
+}
+
+impl<T, const R: usize, const C: usize> IndexMut<(usize, usize)> for Matrix<T, R, C> {
+    fn index_mut(&mut self, (row, column): (usize, usize)) -> &mut T {
+        &mut self.rows[row][column]
+    }
+}
+
+impl<T: Scalar, const R: usize, const C: usize> Add for Matrix<T, R, C> {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Self::new(std::array::from_fn(|i| {
+            (self.row(i) + rhs.row(i)).into_array()
+        }))
+    }
+}
+
+impl<T: Scalar, const R: usize, const C: usize> Sub for Matrix<T, R, C> {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Self::new(std::array::from_fn(|i| {
+            (self.row(i) - rhs.row(i)).into_array()
+        }))
+    }
+}
+
+impl<T: Scalar, const R: usize, const C: usize> Mul<T> for Matrix<T, R, C> {
+    type Output = Self;
+
+    fn mul(self, rhs: T) -> Self::Output {
+        Self::new(self.rows.map(|row| row.map(|a| a * rhs)))
+    }
+}
+
+impl<T: Scalar, const R: usize, const C: usize> Mul<Vector<T, C>> for Matrix<T, R, C> {
+    type Output = Vector<T, R>;
+
+    fn mul(self, rhs: Vector<T, C>) -> Self::Output {
+        Vector::from_array(std::array::from_fn(|i| self.row(i).dot(rhs)))
+    }
+}
+
+impl<T: Scalar, const R: usize, const N: usize, const C: usize> Mul<Matrix<T, N, C>>
+    for Matrix<T, R, N>
+{
+    type Output = Matrix<T, R, C>;
+
+    fn mul(self, rhs: Matrix<T, N, C>) -> Self::Output {
+        Matrix::new(std::array::from_fn(|i| {
+            std::array::from_fn(|j| self.row(i).dot(rhs.column(j)))
+        }))
+    }
+}
+
+#[cfg(test)]
+fn assert_close<const R: usize, const C: usize>(a: Matrix<f64, R, C>, b: Matrix<f64, R, C>) {
+    for i in 0..R {
+        for j in 0..C {
+            assert!((a[(i, j)] - b[(i, j)]).abs() < 1e-9, "{a:?} != {b:?}");
+        }
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn products() {
+    let a = Matrix::new([[1, 2], [3, 4]]);
+    let b = Matrix::new([[5, 6], [7, 8]]);
+    assert_eq!(a * b, Matrix::new([[19, 22], [43, 50]]));
+    assert_eq!(b * a, Matrix::new([[23, 34], [31, 46]]));
+    assert_eq!(a * Matrix::identity(), a);
+
+    let c = Matrix::new([[1, 2, 3], [4, 5, 6]]);
+    let d = Matrix::new([[7, 8], [9, 10], [11, 12]]);
+    assert_eq!(c * d, Matrix::new([[58, 64], [139, 154]]));
+    assert_eq!(c * Vector::from([1, 0, -1]), Vector::new(-2, -2));
+
+    assert_eq!(a + b, Matrix::new([[6, 8], [10, 12]]));
+    assert_eq!(b - a, Matrix::new([[4, 4], [4, 4]]));
+    assert_eq!(a * 3, Matrix::new([[3, 6], [9, 12]]));
+}
+
+#[cfg(test)]
+#[test]
+fn transposing() {
+    let c = Matrix::new([[1, 2, 3], [4, 5, 6]]);
+    assert_eq!(c.transpose(), Matrix::new([[1, 4], [2, 5], [3, 6]]));
+    assert_eq!(c.transpose().transpose(), c);
+    assert_eq!(c.row(1), Vector::from([4, 5, 6]));
+    assert_eq!(c.column(2), Vector::new(3, 6));
+}
+
+#[cfg(test)]
+#[test]
+fn determinants() {
+    assert_eq!(Matrix::new([[1.0, 2.0], [3.0, 4.0]]).determinant(), -2.0);
+    let m = Matrix::new([[6.0, 1.0, 1.0], [4.0, -2.0, 5.0], [2.0, 8.0, 7.0]]);
+    assert!((m.determinant() - -306.0f64).abs() < 1e-9);
+    assert_eq!(Matrix::<f64, 4, 4>::identity().determinant(), 1.0);
+    // The first pivot is zero, so rows have to be swapped.
+    assert_eq!(Matrix::new([[0.0, 1.0], [1.0, 0.0]]).determinant(), -1.0);
+    // The last row is the sum of the first two.
+    let singular = Matrix::new([[1.0, 2.0, 3.0], [4.0, 5.0, 6.0], [5.0, 7.0, 9.0]]);
+    assert_eq!(singular.determinant(), 0.0);
+    assert_eq!((singular * 1e-20).determinant(), 0.0);
+    assert_eq!(Matrix::<f64, 3, 3>::new([[0.0; 3]; 3]).determinant(), 0.0);
+
+    // Small values are not zero.
+    let small = Matrix::new([[1e-15, 0.0], [0.0, 1e-15]]);
+    assert_eq!(small.determinant(), 1e-30);
+}
+
+#[cfg(test)]
+#[test]
+fn inverses() {
+    let m = Matrix::new([[4.0, 7.0], [2.0, 6.0]]);
+    assert_close(
+        m.inverse().unwrap(),
+        Matrix::new([[0.6, -0.7], [-0.2, 0.4]]),
+    );
+
+    let m = Matrix::new([[2.0, -1.0, 0.0], [-1.0, 2.0, -1.0], [0.0, -1.0, 2.0]]);
+    let inverse = m.inverse().unwrap();
+    assert_close(
+        inverse * 4.0,
+        Matrix::new([[3.0, 2.0, 1.0], [2.0, 4.0, 2.0], [1.0, 2.0, 3.0]]),
+    );
+    assert_close(m * inverse, Matrix::identity());
+    assert_close(inverse * m, Matrix::identity());
+
+    let singular = Matrix::new([[1.0, 2.0, 3.0], [4.0, 5.0, 6.0], [5.0, 7.0, 9.0]]);
+    assert_eq!(singular.inverse(), None);
+    assert_eq!((singular * 1e6).inverse(), None);
+
+    let small = Matrix::new([[1e-15, 0.0], [0.0, 1e-15]]);
+    assert_close(small.inverse().unwrap() * 1e-15, Matrix::identity());
+    let m = Matrix::new([[4e-12, 7e-12], [2e-12, 6e-12]]);
+    assert_close(
+        m.inverse().unwrap() * 1e-12,
+        Matrix::new([[0.6, -0.7], [-0.2, 0.4]]),
+    );
+}
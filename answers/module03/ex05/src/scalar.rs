@@ -0,0 +1,61 @@
+//! The numbers that vectors and matrices can be made of.
+
+use std::ops::*;
+
+/// A number that can be added, subtracted, multiplied and divided.
+pub trait Scalar:
+    Copy
+    + PartialEq
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Neg<Output = Self>
+{
+    const ZERO: Self;
+    const ONE: Self;
+}
+
+/// A floating-point number, for the operations that need square roots or exact divisions.
+pub trait Float: Scalar + PartialOrd {
+    /// A relative precision: a result smaller than the values it was computed from times this
+    /// is considered to be zero.
+    const EPSILON: Self;
+
+    fn sqrt(self) -> Self;
+    fn abs(self) -> Self;
+}
+
+macro_rules! impl_scalar {
+    ($zero:literal, $one:literal, $($t:ty)*) => {
+        $(
+            impl Scalar for $t {
+                const ZERO: Self = $zero;
+                const ONE: Self = $one;
+            }
+        )*
+    };
+}
+
+impl_scalar!(0, 1, i8 i16 i32 i64 i128 isize);
+impl_scalar!(0.0, 1.0, f32 f64);
+
+macro_rules! impl_float {
+    ($($t:ident)*) => {
+        $(
+            impl Float for $t {
+                const EPSILON: Self = $t::EPSILON * 16.0;
+
+                fn sqrt(self) -> Self {
+                    self.sqrt()
+                }
+
+                fn abs(self) -> Self {
+                    self.abs()
+                }
+            }
+        )*
+    };
+}
+
+impl_float!(f32 f64);
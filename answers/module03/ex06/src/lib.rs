@@ -1,118 +1,456 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
 use std::ops::Index;
 use std::ops::IndexMut;
+use std::ptr::NonNull;
 
-#[derive(Clone)]
 struct Node<T> {
     value: T,
-    next: Option<Box<Node<T>>>,
+    next: Link<T>,
 }
 
+/// A pointer to a node allocated with `Box`, and owned by the list it is part of.
+type Link<T> = Option<NonNull<Node<T>>>;
+
 impl<T> Node<T> {
-    fn new(value: T) -> Self {
-        Self { value, next: None }
+    /// Allocates a node that is not part of a list yet.
+    fn alloc(value: T) -> NonNull<Self> {
+        NonNull::from(Box::leak(Box::new(Self { value, next: None })))
     }
 
-    fn with_next(value: T, next: Box<Node<T>>) -> Self {
-        Self {
-            value,
-            next: Some(next),
-        }
+    /// Frees a node and returns its value.
+    ///
+    /// # Safety
+    ///
+    /// `node` must come from [`Node::alloc`], and no longer be reachable from a list.
+    unsafe fn free(node: NonNull<Self>) -> T {
+        Box::from_raw(node.as_ptr()).value
     }
 }
 
-#[derive(Clone, Default)]
+/// A singly-linked list, which also knows its last node so that values can be appended
+/// without walking through it.
 pub struct List<T> {
-    head: Option<Box<Node<T>>>,
+    head: Link<T>,
+    tail: Link<T>,
+    len: usize,
+    /// The list owns its nodes.
+    _nodes: PhantomData<Box<Node<T>>>,
 }
 
+// SAFETY: the nodes are only reachable through the list, like the values of a `Vec`.
+unsafe impl<T: Send> Send for List<T> {}
+unsafe impl<T: Sync> Sync for List<T> {}
+
 impl<T> List<T> {
     pub fn new() -> Self {
-        Self { head: None }
+        Self {
+            head: None,
+            tail: None,
+            len: 0,
+            _nodes: PhantomData,
+        }
     }
 
     pub fn push_front(&mut self, value: T) {
-        if let Some(head) = self.head.take() {
-            self.head = Some(Box::new(Node::with_next(value, head)));
-        } else {
-            self.head = Some(Box::new(Node::new(value)));
+        let mut node = Node::alloc(value);
+        // SAFETY: the node was just allocated, and nothing else points to it.
+        unsafe { node.as_mut().next = self.head };
+        self.head = Some(node);
+        if self.tail.is_none() {
+            self.tail = Some(node);
         }
+        self.len += 1;
     }
 
     pub fn push_back(&mut self, value: T) {
-        let mut current = &mut self.head;
-        while let Some(node) = current {
-            current = &mut node.next;
+        let node = Node::alloc(value);
+        match self.tail {
+            // SAFETY: the tail is a node of this list, which `&mut self` lets us modify.
+            Some(mut tail) => unsafe { tail.as_mut().next = Some(node) },
+            None => self.head = Some(node),
         }
-
-        *current = Some(Box::new(Node::new(value)));
+        self.tail = Some(node);
+        self.len += 1;
     }
 
     pub fn count(&self) -> usize {
-        let mut count = 0;
-        let mut current = &self.head;
-        while let Some(node) = current {
-            current = &node.next;
-            count += 1;
+        self.len
+    }
+
+    pub fn get(&self, i: usize) -> Option<&T> {
+        self.iter().nth(i)
+    }
+
+    pub fn get_mut(&mut self, i: usize) -> Option<&mut T> {
+        self.iter_mut().nth(i)
+    }
+
+    pub fn remove_front(&mut self) -> Option<T> {
+        let head = self.head?;
+        // SAFETY: the head is a node of this list, and is unlinked before being freed.
+        unsafe {
+            self.head = head.as_ref().next;
+            if self.head.is_none() {
+                self.tail = None;
+            }
+            self.len -= 1;
+            Some(Node::free(head))
         }
-        count
     }
 
-    pub fn get(&self, mut i: usize) -> Option<&T> {
-        let mut current = &self.head;
-        while let Some(node) = current {
-            if i == 0 {
-                return Some(&node.value);
+    /// Removes the last value. As nodes do not know the one before them, this walks through
+    /// the whole list, in O(n) time.
+    ///
+    /// Removing from the back in constant time would take a doubly-linked list, which this is
+    /// not meant to be: [`std::collections::LinkedList`] is better suited to that use.
+    pub fn remove_back(&mut self) -> Option<T> {
+        let tail = self.tail?;
+        if self.len == 1 {
+            return self.remove_front();
+        }
+
+        let mut before = self.head?;
+        // SAFETY: the nodes are part of this list, and the tail is unlinked before being freed.
+        unsafe {
+            while before.as_ref().next != Some(tail) {
+                before = before.as_ref().next?;
             }
-            current = &node.next;
-            i -= 1;
+            before.as_mut().next = None;
+            self.tail = Some(before);
+            self.len -= 1;
+            Some(Node::free(tail))
         }
-        None
     }
 
-    pub fn get_mut(&mut self, mut i: usize) -> Option<&mut T> {
-        let mut current = &mut self.head;
-        while let Some(node) = current {
-            if i == 0 {
-                return Some(&mut node.value);
+    pub fn clear(&mut self) {
+        while self.remove_front().is_some() {}
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            next: self.head,
+            _list: PhantomData,
+        }
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+        IterMut {
+            next: self.head,
+            _list: PhantomData,
+        }
+    }
+
+    /// Returns a cursor on the first value, or past the end if the list is empty.
+    pub fn cursor_front_mut(&mut self) -> CursorMut<'_, T> {
+        CursorMut {
+            current: self.head,
+            before: None,
+            index: 0,
+            list: self,
+        }
+    }
+
+    /// Returns a cursor on value `i`, or past the end if `i` is the length of the list.
+    ///
+    /// # Panics
+    ///
+    /// If `i` is larger than the length of the list.
+    fn cursor_at_mut(&mut self, i: usize) -> CursorMut<'_, T> {
+        assert!(i <= self.len, "tried to access out of bound index {i}");
+        let mut cursor = self.cursor_front_mut();
+        while cursor.index < i {
+            cursor.move_next();
+        }
+        cursor
+    }
+
+    /// Inserts `value` so that it ends up at index `i`.
+    ///
+    /// # Panics
+    ///
+    /// If `i` is larger than the length of the list.
+    pub fn insert(&mut self, i: usize, value: T) {
+        self.cursor_at_mut(i).insert_before(value);
+    }
+
+    /// Removes the value at index `i`, if there is one.
+    pub fn remove(&mut self, i: usize) -> Option<T> {
+        if i >= self.len {
+            return None;
+        }
+        self.cursor_at_mut(i).remove_current()
+    }
+
+    /// Moves the values from index `at` on into a new list.
+    ///
+    /// # Panics
+    ///
+    /// If `at` is larger than the length of the list.
+    pub fn split_off(&mut self, at: usize) -> Self {
+        assert!(at <= self.len, "tried to split at out of bound index {at}");
+        if at == 0 {
+            return std::mem::take(self);
+        }
+
+        let mut last = self.head.unwrap();
+        // SAFETY: the first `at` nodes exist, and the rest are moved to the new list.
+        unsafe {
+            for _ in 1..at {
+                last = last.as_ref().next.unwrap();
             }
-            current = &mut node.next;
-            i -= 1;
+            let rest = Self {
+                head: last.as_mut().next.take(),
+                tail: if at == self.len { None } else { self.tail },
+                len: self.len - at,
+                _nodes: PhantomData,
+            };
+            self.tail = Some(last);
+            self.len = at;
+            rest
         }
-        None
     }
 
-    pub fn remove_front(&mut self) -> Option<T> {
-        if let Some(head) = self.head.take() {
-            self.head = head.next;
-            Some(head.value)
+    /// Moves the values of `other` to the back of this list, leaving it empty.
+    pub fn append(&mut self, other: &mut Self) {
+        let other = std::mem::take(other);
+        let Some(head) = other.head else {
+            return;
+        };
+        match self.tail {
+            // SAFETY: the tail is a node of this list, which `&mut self` lets us modify.
+            Some(mut tail) => unsafe { tail.as_mut().next = Some(head) },
+            None => self.head = Some(head),
+        }
+        self.tail = other.tail;
+        self.len += other.len;
+        // The nodes now belong to this list.
+        std::mem::forget(other);
+    }
+
+    pub fn reverse(&mut self) {
+        let mut reversed: Link<T> = None;
+        let mut next = self.head;
+        while let Some(mut node) = next {
+            // SAFETY: each node of this list is visited once, and relinked to the previous one.
+            unsafe {
+                next = node.as_ref().next;
+                node.as_mut().next = reversed;
+            }
+            reversed = Some(node);
+        }
+        self.tail = self.head;
+        self.head = reversed;
+    }
+
+    /// Sorts the values, keeping those that are equal in the same order.
+    pub fn sort(&mut self)
+    where
+        T: Ord,
+    {
+        self.sort_by(T::cmp);
+    }
+
+    /// Sorts the values with a merge sort, keeping those that are equal in the same order.
+    ///
+    /// Nodes are relinked rather than values moved, and runs are merged bottom-up, so that no
+    /// memory is needed besides the list itself. Runs are merged in place, so that the list
+    /// stays whole, only in another order, if `compare` panics.
+    pub fn sort_by(&mut self, mut compare: impl FnMut(&T, &T) -> Ordering) {
+        let mut width = 1;
+        while width < self.len {
+            let mut link: *mut Link<T> = &raw mut self.head;
+            // SAFETY: `link` is always the head of this list or the `next` of one of its
+            // nodes, and `merge_runs` only relinks nodes of the list.
+            unsafe {
+                while (*link).is_some() {
+                    link = merge_runs(link, width, &mut self.tail, &mut compare);
+                }
+            }
+            width *= 2;
+        }
+    }
+}
+
+/// Merges the run of `width` nodes starting at `*link` with the run of up to `width` nodes
+/// that follows, taking from the first run when values are equal, and returns the link after
+/// the merged run.
+///
+/// Nodes of the second run are moved one at a time, so that every node is still linked when
+/// `compare` is called.
+///
+/// # Safety
+///
+/// `*link` must be the start of a list whose last node is `tail`. The nodes must be allocated,
+/// and not borrowed.
+unsafe fn merge_runs<T>(
+    mut link: *mut Link<T>,
+    width: usize,
+    tail: &mut Link<T>,
+    compare: &mut impl FnMut(&T, &T) -> Ordering,
+) -> *mut Link<T> {
+    // Nodes of the second run are taken from after the last one of the first run.
+    let mut last_left = (*link).unwrap();
+    for _ in 1..width {
+        match (*last_left.as_ptr()).next {
+            Some(next) => last_left = next,
+            None => return &raw mut (*last_left.as_ptr()).next,
+        }
+    }
+
+    let (mut left, mut right) = (width, width);
+    while left > 0 && right > 0 {
+        let (Some(l), Some(r)) = (*link, (*last_left.as_ptr()).next) else {
+            break;
+        };
+        if compare(&(*r.as_ptr()).value, &(*l.as_ptr()).value) == Ordering::Less {
+            (*last_left.as_ptr()).next = (*r.as_ptr()).next.take();
+            if (*last_left.as_ptr()).next.is_none() {
+                *tail = Some(last_left);
+            }
+            (*r.as_ptr()).next = Some(l);
+            *link = Some(r);
+            link = &raw mut (*r.as_ptr()).next;
+            right -= 1;
         } else {
-            None
+            link = &raw mut (*l.as_ptr()).next;
+            left -= 1;
         }
     }
 
-    pub fn remove_back(&mut self) -> Option<T> {
-        #[allow(clippy::question_mark)]
-        if self.head.is_none() {
-            return None;
+    // The rest of either run is already in place.
+    if left > 0 {
+        return &raw mut (*last_left.as_ptr()).next;
+    }
+    for _ in 0..right {
+        match *link {
+            Some(next) => link = &raw mut (*next.as_ptr()).next,
+            None => break,
+        }
+    }
+    link
+}
+
+/// A position in a [`List`] that can move forward and edit the list around it.
+///
+/// The cursor is either on a value, or past the end of the list.
+pub struct CursorMut<'a, T> {
+    list: &'a mut List<T>,
+    /// The node before the current one, to link new nodes to.
+    before: Link<T>,
+    current: Link<T>,
+    index: usize,
+}
+
+// SAFETY: the cursor only gives access to the list like a `&mut List<T>` would.
+unsafe impl<T: Send> Send for CursorMut<'_, T> {}
+unsafe impl<T: Sync> Sync for CursorMut<'_, T> {}
+
+impl<T> CursorMut<'_, T> {
+    /// The index of the current value, or the length of the list when past the end.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn current(&mut self) -> Option<&mut T> {
+        // SAFETY: the node is part of the list, which the cursor borrows mutably.
+        self.current
+            .map(|mut node| unsafe { &mut node.as_mut().value })
+    }
+
+    /// Moves to the next value. Does nothing past the end.
+    pub fn move_next(&mut self) {
+        if let Some(node) = self.current {
+            self.before = Some(node);
+            // SAFETY: the node is part of the list, which the cursor borrows.
+            self.current = unsafe { node.as_ref().next };
+            self.index += 1;
         }
+    }
 
-        let mut current = &mut self.head;
-        while current.is_some() {
-            if current.as_ref().unwrap().next.is_none() {
-                break;
+    /// Links `node` between the one before the cursor and the current one.
+    fn link_before(&mut self, mut node: NonNull<Node<T>>) {
+        // SAFETY: the nodes are part of the list, which the cursor borrows mutably, and `node`
+        // is not linked yet.
+        unsafe {
+            node.as_mut().next = self.current;
+            match self.before {
+                Some(mut before) => before.as_mut().next = Some(node),
+                None => self.list.head = Some(node),
             }
-            current = &mut current.as_mut().unwrap().next;
         }
+        if self.current.is_none() {
+            self.list.tail = Some(node);
+        }
+        self.list.len += 1;
+    }
 
-        #[allow(clippy::manual_map)]
-        match current.take() {
-            Some(node) => Some(node.value),
-            None => None,
+    /// Inserts `value` before the current value, or at the back when past the end. The cursor
+    /// stays on the same value.
+    pub fn insert_before(&mut self, value: T) {
+        let node = Node::alloc(value);
+        self.link_before(node);
+        self.before = Some(node);
+        self.index += 1;
+    }
+
+    /// Inserts `value` after the current value, or at the back when past the end. The cursor
+    /// stays on the same value.
+    pub fn insert_after(&mut self, value: T) {
+        let Some(mut current) = self.current else {
+            return self.insert_before(value);
+        };
+        let mut node = Node::alloc(value);
+        // SAFETY: the current node is part of the list, which the cursor borrows mutably, and
+        // `node` is not linked yet.
+        unsafe {
+            node.as_mut().next = current.as_ref().next;
+            current.as_mut().next = Some(node);
         }
+        if self.list.tail == Some(current) {
+            self.list.tail = Some(node);
+        }
+        self.list.len += 1;
     }
 
-    pub fn clear(&mut self) {
-        self.head = None;
+    /// Removes the current value, moving the cursor to the next one.
+    pub fn remove_current(&mut self) -> Option<T> {
+        let current = self.current?;
+        // SAFETY: the nodes are part of the list, which the cursor borrows mutably, and the
+        // current node is unlinked before being freed.
+        unsafe {
+            self.current = current.as_ref().next;
+            match self.before {
+                Some(mut before) => before.as_mut().next = self.current,
+                None => self.list.head = self.current,
+            }
+            if self.current.is_none() {
+                self.list.tail = self.before;
+            }
+            self.list.len -= 1;
+            Some(Node::free(current))
+        }
+    }
+}
+
+impl<T> Drop for List<T> {
+    /// Frees the nodes one by one, as dropping them recursively could overflow the stack.
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+impl<T> Default for List<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Clone> Clone for List<T> {
+    fn clone(&self) -> Self {
+        self.iter().cloned().collect()
     }
 }
 
@@ -136,6 +474,129 @@ impl<T> IndexMut<usize> for List<T> {
     }
 }
 
+/// Iterates over the values of a [`List`], from front to back.
+pub struct Iter<'a, T> {
+    next: Link<T>,
+    _list: PhantomData<&'a List<T>>,
+}
+
+// SAFETY: the iterator only gives access to the values like a `&List<T>` would.
+unsafe impl<T: Sync> Send for Iter<'_, T> {}
+unsafe impl<T: Sync> Sync for Iter<'_, T> {}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // SAFETY: the list is borrowed for `'a`, so its nodes live and are not modified.
+        let node = unsafe { self.next?.as_ref() };
+        self.next = node.next;
+        Some(&node.value)
+    }
+}
+
+/// Iterates over mutable references to the values of a [`List`], from front to back.
+pub struct IterMut<'a, T> {
+    next: Link<T>,
+    _list: PhantomData<&'a mut List<T>>,
+}
+
+// SAFETY: the iterator only gives access to the values like a `&mut List<T>` would.
+unsafe impl<T: Send> Send for IterMut<'_, T> {}
+unsafe impl<T: Sync> Sync for IterMut<'_, T> {}
+
+impl<'a, T> Iterator for IterMut<'a, T> {
+    type Item = &'a mut T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // SAFETY: the list is borrowed mutably for `'a`, and each node is only yielded once.
+        let node = unsafe { self.next?.as_mut() };
+        self.next = node.next;
+        Some(&mut node.value)
+    }
+}
+
+/// Takes the values out of a [`List`], from front to back.
+pub struct IntoIter<T> {
+    list: List<T>,
+}
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.list.remove_front()
+    }
+}
+
+impl<T> IntoIterator for List<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter { list: self }
+    }
+}
+
+impl<'a, T> IntoIterator for &'a List<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a mut List<T> {
+    type Item = &'a mut T;
+    type IntoIter = IterMut<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl<T> Extend<T> for List<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for value in iter {
+            self.push_back(value);
+        }
+    }
+}
+
+impl<T> FromIterator<T> for List<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut list = Self::new();
+        list.extend(iter);
+        list
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for List<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self).finish()
+    }
+}
+
+impl<T: PartialEq> PartialEq for List<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.iter().eq(other)
+    }
+}
+
+impl<T: Eq> Eq for List<T> {}
+
+impl<T: Hash> Hash for List<T> {
+    /// Hashes the length first, so that lists of lists that only differ in where values are
+    /// split do not collide.
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.len.hash(state);
+        for value in self {
+            value.hash(state);
+        }
+    }
+}
+
 #[cfg(test)]
 #[test]
 fn default_list_is_empty() {
@@ -167,3 +628,333 @@ fn out_of_bound_access_panics() {
 
     assert_eq!(list[10], 42);
 }
+
+#[cfg(test)]
+#[test]
+fn iterators() {
+    let mut list: List<i32> = (1..=4).collect();
+    assert_eq!(list.iter().copied().collect::<Vec<_>>(), [1, 2, 3, 4]);
+
+    for value in &mut list {
+        *value *= 10;
+    }
+    list.iter_mut().for_each(|value| *value += 1);
+    assert_eq!((&list).into_iter().sum::<i32>(), 104);
+
+    list.extend([50, 60]);
+    assert_eq!(list.count(), 6);
+    assert_eq!(
+        list.into_iter().collect::<Vec<_>>(),
+        [11, 21, 31, 41, 50, 60]
+    );
+
+    let mut empty: List<String> = List::new();
+    assert_eq!(empty.iter().next(), None);
+    empty.extend(["a", "b"].map(String::from));
+    assert_eq!(empty[1], "b");
+}
+
+#[cfg(test)]
+#[test]
+fn equality_and_debug() {
+    use std::collections::hash_map::DefaultHasher;
+
+    let hash = |list: &List<List<u8>>| {
+        let mut hasher = DefaultHasher::new();
+        list.hash(&mut hasher);
+        hasher.finish()
+    };
+
+    let a: List<u8> = [1, 2, 3].into_iter().collect();
+    let b: List<u8> = [1, 2, 3].into_iter().collect();
+    let c: List<u8> = [1, 2].into_iter().collect();
+    assert_eq!(a, b);
+    assert_ne!(a, c);
+    assert_ne!(c, a);
+    assert_eq!(format!("{a:?}"), "[1, 2, 3]");
+    assert_eq!(format!("{:?}", List::<u8>::new()), "[]");
+
+    let nested = |parts: &[&[u8]]| -> List<List<u8>> {
+        parts
+            .iter()
+            .map(|part| part.iter().copied().collect())
+            .collect()
+    };
+    assert_eq!(
+        hash(&nested(&[&[1, 2], &[3]])),
+        hash(&nested(&[&[1, 2], &[3]]))
+    );
+    assert_ne!(
+        hash(&nested(&[&[1, 2], &[3]])),
+        hash(&nested(&[&[1], &[2, 3]]))
+    );
+}
+
+#[cfg(test)]
+#[test]
+fn both_ends() {
+    let mut list = List::new();
+    list.push_back(2);
+    list.push_front(1);
+    list.push_back(3);
+    assert_eq!(list.remove_back(), Some(3));
+    list.push_back(4);
+    assert_eq!(list.iter().copied().collect::<Vec<_>>(), [1, 2, 4]);
+    assert_eq!(list.count(), 3);
+
+    assert_eq!(list.remove_front(), Some(1));
+    assert_eq!(list.remove_back(), Some(4));
+    assert_eq!(list.remove_back(), Some(2));
+    assert_eq!(list.remove_back(), None);
+    assert_eq!(list.remove_front(), None);
+    assert_eq!(list.count(), 0);
+
+    // The tail was reset along with the head.
+    list.push_back(5);
+    list.push_front(6);
+    assert_eq!(list.iter().copied().collect::<Vec<_>>(), [6, 5]);
+    list.clear();
+    list.push_front(7);
+    list.push_back(8);
+    assert_eq!(list.iter().copied().collect::<Vec<_>>(), [7, 8]);
+}
+
+#[cfg(test)]
+#[test]
+fn drops_values() {
+    use std::rc::Rc;
+
+    let value = Rc::new(());
+    let mut list: List<Rc<()>> = (0..10).map(|_| Rc::clone(&value)).collect();
+    drop(list.remove_back());
+    let mut iter = list.clone().into_iter();
+    iter.next();
+    assert_eq!(Rc::strong_count(&value), 1 + 9 + 8);
+    drop(iter);
+    drop(list);
+    assert_eq!(Rc::strong_count(&value), 1);
+}
+
+#[cfg(test)]
+#[test]
+fn ten_million_nodes() {
+    let mut list = List::new();
+    for i in 0..10_000_000u32 {
+        list.push_back(i);
+    }
+    assert_eq!(list.count(), 10_000_000);
+    assert_eq!(list.remove_front(), Some(0));
+    drop(list);
+}
+
+#[cfg(test)]
+fn to_vec<T: Clone>(list: &List<T>) -> Vec<T> {
+    list.iter().cloned().collect()
+}
+
+#[cfg(test)]
+#[test]
+fn cursor() {
+    let mut list: List<i32> = [1, 2, 3].into_iter().collect();
+    let mut cursor = list.cursor_front_mut();
+    assert_eq!(cursor.current(), Some(&mut 1));
+    cursor.insert_before(0);
+    cursor.insert_after(10);
+    assert_eq!(cursor.index(), 1);
+    cursor.move_next();
+    assert_eq!(cursor.current(), Some(&mut 10));
+    *cursor.current().unwrap() = 15;
+    assert_eq!(cursor.remove_current(), Some(15));
+    assert_eq!(cursor.current(), Some(&mut 2));
+    cursor.move_next();
+    cursor.move_next();
+    assert_eq!(cursor.current(), None);
+    assert_eq!(cursor.index(), 4);
+    cursor.insert_after(4);
+    cursor.insert_before(5);
+    cursor.move_next();
+    assert_eq!(cursor.index(), 6);
+    assert_eq!(cursor.remove_current(), None);
+    assert_eq!(to_vec(&list), [0, 1, 2, 3, 4, 5]);
+    assert_eq!(list.count(), 6);
+
+    // Removing the last value moves the tail back.
+    let mut cursor = list.cursor_front_mut();
+    while cursor.index() < 5 {
+        cursor.move_next();
+    }
+    assert_eq!(cursor.remove_current(), Some(5));
+    cursor.insert_after(6);
+    list.push_back(7);
+    assert_eq!(to_vec(&list), [0, 1, 2, 3, 4, 6, 7]);
+
+    let mut empty: List<i32> = List::new();
+    let mut cursor = empty.cursor_front_mut();
+    assert_eq!(cursor.remove_current(), None);
+    cursor.insert_after(1);
+    empty.push_front(0);
+    empty.push_back(2);
+    assert_eq!(to_vec(&empty), [0, 1, 2]);
+}
+
+#[cfg(test)]
+#[test]
+fn insert_and_remove() {
+    let mut list: List<char> = "ace".chars().collect();
+    list.insert(1, 'b');
+    list.insert(3, 'd');
+    list.insert(5, 'f');
+    list.insert(0, '_');
+    assert_eq!(to_vec(&list), ['_', 'a', 'b', 'c', 'd', 'e', 'f']);
+
+    assert_eq!(list.remove(0), Some('_'));
+    assert_eq!(list.remove(5), Some('f'));
+    assert_eq!(list.remove(5), None);
+    assert_eq!(list.remove(2), Some('c'));
+    list.push_back('g');
+    assert_eq!(to_vec(&list), ['a', 'b', 'd', 'e', 'g']);
+}
+
+#[cfg(test)]
+#[test]
+#[should_panic(expected = "tried to access out of bound index 4")]
+fn out_of_bound_insert_panics() {
+    let mut list: List<u32> = (0..3).collect();
+    list.insert(4, 4);
+}
+
+#[cfg(test)]
+#[test]
+fn splitting_and_appending() {
+    let mut list: List<u32> = (0..6).collect();
+    let mut back = list.split_off(4);
+    assert_eq!(to_vec(&list), [0, 1, 2, 3]);
+    assert_eq!(to_vec(&back), [4, 5]);
+    assert_eq!((list.count(), back.count()), (4, 2));
+    list.push_back(10);
+    back.push_back(11);
+    assert_eq!(to_vec(&list), [0, 1, 2, 3, 10]);
+    assert_eq!(to_vec(&back), [4, 5, 11]);
+
+    let mut all = list.split_off(0);
+    assert_eq!(list.count(), 0);
+    let mut nothing = all.split_off(5);
+    assert_eq!(nothing.count(), 0);
+    nothing.push_back(1);
+    assert_eq!(to_vec(&nothing), [1]);
+
+    all.append(&mut back);
+    assert_eq!(back.count(), 0);
+    all.append(&mut List::new());
+    all.push_back(12);
+    assert_eq!(to_vec(&all), [0, 1, 2, 3, 10, 4, 5, 11, 12]);
+    list.append(&mut all);
+    list.push_back(13);
+    assert_eq!(list.count(), 10);
+    assert_eq!(list[9], 13);
+}
+
+#[cfg(test)]
+#[test]
+fn reversing() {
+    let mut list: List<u32> = (0..5).collect();
+    list.reverse();
+    list.push_back(10);
+    assert_eq!(to_vec(&list), [4, 3, 2, 1, 0, 10]);
+
+    let mut empty: List<u32> = List::new();
+    empty.reverse();
+    empty.push_back(1);
+    assert_eq!(to_vec(&empty), [1]);
+}
+
+#[cfg(test)]
+#[test]
+fn sorting() {
+    // A value that cannot be cloned, compared by `key` only.
+    #[derive(Debug)]
+    struct Item {
+        key: u32,
+        order: usize,
+    }
+
+    let mut state = 12345u32;
+    let mut keys = Vec::new();
+    for _ in 0..1000 {
+        state = state.wrapping_mul(1103515245).wrapping_add(12345);
+        keys.push(state >> 16 & 0xF);
+    }
+
+    for len in [0, 1, 2, 3, 7, 64, 1000] {
+        let mut list: List<Item> = keys[..len]
+            .iter()
+            .enumerate()
+            .map(|(order, &key)| Item { key, order })
+            .collect();
+        list.sort_by(|a, b| a.key.cmp(&b.key));
+
+        let mut expected: Vec<_> = keys[..len].iter().copied().enumerate().collect();
+        expected.sort_by_key(|&(_, key)| key);
+        let sorted: Vec<_> = list.iter().map(|item| (item.order, item.key)).collect();
+        assert_eq!(sorted, expected, "{len} values");
+
+        list.push_back(Item { key: 99, order: 0 });
+        assert_eq!(list.count(), len + 1);
+        assert_eq!(list[len].key, 99);
+    }
+
+    let mut words: List<&str> = ["pear", "apple", "fig", "apple"].into_iter().collect();
+    words.sort();
+    assert_eq!(to_vec(&words), ["apple", "apple", "fig", "pear"]);
+}
+
+#[cfg(test)]
+#[test]
+fn sorting_survives_panics() {
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    for panic_at in 1..20 {
+        let mut list: List<u32> = [5, 3, 1, 4, 2, 0, 6].into_iter().collect();
+        let mut calls = 0;
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            list.sort_by(|a, b| {
+                calls += 1;
+                assert_ne!(calls, panic_at, "comparator panicked");
+                a.cmp(b)
+            })
+        }));
+        assert_eq!(result.is_err(), panic_at <= calls);
+
+        // Every value is still in the list, and it can still be edited.
+        let mut values = to_vec(&list);
+        values.sort();
+        assert_eq!(values, [0, 1, 2, 3, 4, 5, 6]);
+        list.push_back(7);
+        list.insert(5, 8);
+        assert_eq!(list.count(), 9);
+        list.sort();
+        assert_eq!(to_vec(&list), [0, 1, 2, 3, 4, 5, 6, 7, 8]);
+        assert_eq!(list.remove_back(), Some(8));
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn send_and_sync() {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<List<String>>();
+    assert_send_sync::<Iter<'_, String>>();
+    assert_send_sync::<IterMut<'_, String>>();
+    assert_send_sync::<CursorMut<'_, String>>();
+
+    let mut list: List<u32> = (0..100).collect();
+    std::thread::scope(|scope| {
+        let iter = list.iter_mut();
+        scope.spawn(move || iter.for_each(|value| *value *= 2));
+    });
+    std::thread::scope(|scope| {
+        let iter = list.iter();
+        let sum = scope.spawn(move || iter.sum::<u32>()).join().unwrap();
+        assert_eq!(sum, 9900);
+    });
+}
@@ -29,13 +29,98 @@ pub fn encode_csv<R: Record>(records: &[R]) -> Result<String, EncodingError> {
 pub fn decode_csv<R: Record>(contents: &str) -> Result<Vec<R>, DecodingError> {
     let mut result = Vec::new();
 
-    for line in contents.lines() {
+    for line in split_records(contents)? {
         result.push(R::decode(line)?);
     }
 
     Ok(result)
 }
 
+/// Splits `contents` into records, which end at line breaks outside of quoted fields.
+///
+/// Like [`str::lines`], a trailing `\r` is removed from each record, and the last one may
+/// lack a line break.
+fn split_records(contents: &str) -> Result<Vec<&str>, DecodingError> {
+    let mut records = Vec::new();
+    let mut quoted = false;
+    let mut start = 0;
+
+    for (i, c) in contents.char_indices() {
+        match c {
+            // An escaped quote toggles this twice.
+            '"' => quoted = !quoted,
+            '\n' if !quoted => {
+                let record = &contents[start..i];
+                records.push(record.strip_suffix('\r').unwrap_or(record));
+                start = i + 1;
+            }
+            _ => (),
+        }
+    }
+
+    if quoted {
+        return Err(DecodingError);
+    }
+    if start < contents.len() {
+        let record = &contents[start..];
+        records.push(record.strip_suffix('\r').unwrap_or(record));
+    }
+    Ok(records)
+}
+
+/// Encodes `field`, between quotes if it contains characters that have a meaning in CSV.
+///
+/// Quotes within quoted fields are doubled, as described in RFC 4180.
+pub fn encode_field<F: Field>(field: &F, target: &mut String) -> Result<(), EncodingError> {
+    let mut encoded = String::new();
+    field.encode(&mut encoded)?;
+
+    if encoded.contains([',', '"', '\n', '\r']) {
+        target.push('"');
+        target.push_str(&encoded.replace('"', "\"\""));
+        target.push('"');
+    } else {
+        target.push_str(&encoded);
+    }
+    Ok(())
+}
+
+/// Splits a record into its fields, removing the quotes around them.
+pub fn split_fields(line: &str) -> Result<Vec<String>, DecodingError> {
+    let mut fields = Vec::new();
+    let mut chars = line.chars().peekable();
+
+    loop {
+        let mut field = String::new();
+        if chars.next_if_eq(&'"').is_some() {
+            loop {
+                match chars.next() {
+                    Some('"') if chars.next_if_eq(&'"').is_some() => field.push('"'),
+                    Some('"') => break,
+                    Some(c) => field.push(c),
+                    None => return Err(DecodingError),
+                }
+            }
+        } else {
+            while let Some(c) = chars.next_if(|&c| c != ',') {
+                // Quotes are only allowed around fields.
+                if c == '"' {
+                    return Err(DecodingError);
+                }
+                field.push(c);
+            }
+        }
+        fields.push(field);
+
+        match chars.next() {
+            Some(',') => (),
+            None => return Ok(fields),
+            // Something follows the closing quote.
+            Some(_) => return Err(DecodingError),
+        }
+    }
+}
+
 macro_rules! impl_field_for_int {
     ( $( $t:ty ),* $(,)? ) => {
         $(
@@ -62,10 +147,6 @@ impl_field_for_int!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isi
 
 impl Field for String {
     fn encode(&self, target: &mut String) -> Result<(), EncodingError> {
-        if self.contains('\n') || self.contains(',') {
-            return Err(EncodingError);
-        }
-
         target.push_str(self);
         Ok(())
     }
@@ -99,32 +180,32 @@ impl<T: Field> Field for Option<T> {
 macro_rules! impl_record {
     ( $ident:ident ( $first_f:ident, $( $f:ident ),* $(,)? )) => {
         impl $crate::Record for $ident {
-            fn encode(&self, target: &mut String) -> Result<(), EncodingError> {
-                $crate::Field::encode(&self.$first_f, target)?;
+            fn encode(&self, target: &mut String) -> Result<(), $crate::EncodingError> {
+                $crate::encode_field(&self.$first_f, target)?;
                 $(
                     target.push(',');
-                    $crate::Field::encode(&self.$f, target)?;
+                    $crate::encode_field(&self.$f, target)?;
                 )*
                 Ok(())
             }
 
-            fn decode(line: &str) -> Result<Self, DecodingError> {
-                let mut splits = line.split(',');
+            fn decode(line: &str) -> Result<Self, $crate::DecodingError> {
+                let mut splits = $crate::split_fields(line)?.into_iter();
 
                 let $first_f = match splits.next() {
-                    Some(field) => $crate::Field::decode(field)?,
+                    Some(field) => $crate::Field::decode(&field)?,
                     None => return Err($crate::DecodingError),
                 };
 
                 $(
                     let $f = match splits.next() {
-                        Some(field) => $crate::Field::decode(field)?,
+                        Some(field) => $crate::Field::decode(&field)?,
                         None => return Err($crate::DecodingError),
                     };
                 )*
 
                 if splits.next().is_some() {
-                    return Err(DecodingError);
+                    return Err($crate::DecodingError);
                 }
 
                 Ok(Self {
@@ -212,12 +293,14 @@ fn decoding_error() {
     decode_csv::<User>(csv).unwrap_err();
 }
 
+#[cfg(test)]
 struct MyType {
     id: u32,
     name: String,
 }
 
 // ez
+#[cfg(test)]
 impl_record!(MyType(id, name));
 
 #[cfg(test)]
@@ -248,3 +331,85 @@ fn test_impl_record() {
         "
     );
 }
+
+#[cfg(test)]
+#[test]
+fn quoting() {
+    let users = [
+        User {
+            name: "Doe, John".into(),
+            age: 40,
+        },
+        User {
+            name: "the \"best\"".into(),
+            age: 1,
+        },
+        User {
+            name: "two\nlines".into(),
+            age: 2,
+        },
+        User {
+            name: "\"".into(),
+            age: 3,
+        },
+        User {
+            name: "".into(),
+            age: 4,
+        },
+    ];
+
+    let csv = encode_csv(&users).unwrap();
+    assert_eq!(
+        csv,
+        "\
+        \"Doe, John\",40\n\
+        \"the \"\"best\"\"\",1\n\
+        \"two\nlines\",2\n\
+        \"\"\"\",3\n\
+        ,4\n\
+        "
+    );
+    assert_eq!(decode_csv::<User>(&csv).unwrap(), users);
+}
+
+#[cfg(test)]
+#[test]
+fn decode_quoted() {
+    // Any field may be quoted, and lines may end with CRLF.
+    let csv = "\"plain\",\"7\"\r\n\"a,\r\nb\",8\r\n\"\",9";
+    assert_eq!(
+        decode_csv::<User>(csv).unwrap(),
+        [
+            User {
+                name: "plain".into(),
+                age: 7
+            },
+            User {
+                name: "a,\r\nb".into(),
+                age: 8
+            },
+            User {
+                name: "".into(),
+                age: 9
+            },
+        ]
+    );
+
+    assert_eq!(split_fields("a,,\"\"").unwrap(), ["a", "", ""]);
+    assert_eq!(split_fields("").unwrap(), [""]);
+    assert_eq!(split_fields("\",\"").unwrap(), [","]);
+}
+
+#[cfg(test)]
+#[test]
+fn decode_malformed() {
+    // Unterminated quotes.
+    decode_csv::<User>("\"hello,2\n").unwrap_err();
+    decode_csv::<User>("hello,2\n\"bye,3").unwrap_err();
+    // Text after a closing quote.
+    decode_csv::<User>("\"hello\"x,2\n").unwrap_err();
+    // Quotes inside an unquoted field.
+    decode_csv::<User>("hel\"lo,2\n").unwrap_err();
+    // The comma is quoted, so there is a single field.
+    decode_csv::<User>("\"hello,2\"\n").unwrap_err();
+}